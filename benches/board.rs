@@ -0,0 +1,145 @@
+//! Compares the throughput of generating a board and flood-filling it, on the flat `Board` the game uses
+//! and on the `Vec<Vec<Tile>>` it replaced. The flat board goes through the same library calls as the game,
+//! the nested one through a copy of the old code. Both place the same amount of mines with the same seed.
+//!
+//! Run with `cargo bench --bench board`.
+
+use std::time::{Duration, Instant};
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use rustsweeper::{
+    board::Board,
+    topology::{Neighbourhood, Topology},
+};
+
+const COLS: usize = 1000;
+const ROWS: usize = 1000;
+const RUNS: u32 = 10;
+
+/// The offsets of the neighbours of the old representation.
+const OFFSETS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+/// The tile of the old representation: three bools and the number.
+#[derive(Copy, Clone, Default)]
+struct NestedTile {
+    is_a_mine: bool,
+    is_revealed: bool,
+    is_flagged: bool,
+    number: Option<u8>,
+}
+
+/// The neighbours of (x, y) on the old `COLS` x `ROWS` board.
+fn nested_neighbours(x: usize, y: usize) -> impl Iterator<Item = (usize, usize)> {
+    OFFSETS.iter().filter_map(move |&(xd, yd)| {
+        let (nx, ny) = (x as isize + xd, y as isize + yd);
+        if nx >= 0 && ny >= 0 && (nx as usize) < COLS && (ny as usize) < ROWS {
+            Some((nx as usize, ny as usize))
+        } else {
+            None
+        }
+    })
+}
+
+/// Generates a board the way the game does: mines on random slots, then the numbers around them.
+fn generate_flat(density: f64) -> Board {
+    let mut board = Board::new(COLS, ROWS);
+    let mut slots: Vec<usize> = (0..board.len()).collect();
+    let mines = (board.len() as f64 * density) as usize;
+    board
+        .place_mines(&mut slots, mines, &mut StdRng::seed_from_u64(0))
+        .unwrap();
+    board.set_numbers(&Topology::Square, Neighbourhood::Adjacent);
+    board
+}
+
+fn generate_nested(density: f64) -> Vec<Vec<NestedTile>> {
+    let mut board = vec![vec![NestedTile::default(); ROWS]; COLS];
+    let mut tiles: Vec<(usize, usize)> = (0..ROWS)
+        .flat_map(|y| (0..COLS).map(move |x| (x, y)))
+        .collect();
+    let mines = ((COLS * ROWS) as f64 * density) as usize;
+    let (mines, _) = tiles.partial_shuffle(&mut StdRng::seed_from_u64(0), mines);
+    for &(x, y) in mines.iter() {
+        board[x][y].is_a_mine = true;
+    }
+    for y in 0..ROWS {
+        for x in 0..COLS {
+            if !board[x][y].is_a_mine {
+                let number = nested_neighbours(x, y)
+                    .filter(|&(nx, ny)| board[nx][ny].is_a_mine)
+                    .count() as u8;
+                board[x][y].number = Some(number).filter(|&n| n > 0);
+            }
+        }
+    }
+    board
+}
+
+/// Reveals the area around the given tile, like opening an empty tile. Returns the amount of revealed tiles.
+fn flood_fill_nested(board: &mut [Vec<NestedTile>], start: (usize, usize)) -> usize {
+    let mut revealed = 0;
+    let mut stack = vec![start];
+    while let Some((x, y)) = stack.pop() {
+        let tile = &mut board[x][y];
+        if tile.is_revealed || tile.is_flagged || tile.is_a_mine {
+            continue;
+        }
+        tile.is_revealed = true;
+        revealed += 1;
+        if tile.number.is_none() {
+            stack.extend(nested_neighbours(x, y));
+        }
+    }
+    revealed
+}
+
+/// Runs the given function `RUNS` times and prints how long a run took on average, and the tiles per second.
+/// The setup isn't timed.
+fn bench<S, T>(name: &str, mut setup: impl FnMut() -> S, mut run: impl FnMut(S) -> T) {
+    let mut total = Duration::default();
+    for _ in 0..RUNS {
+        let input = setup();
+        let start = Instant::now();
+        std::hint::black_box(run(input));
+        total += start.elapsed();
+    }
+    let per_run = total / RUNS;
+    println!(
+        "{:<24} {:>8.2} ms/run {:>8.1} Mtiles/s",
+        name,
+        per_run.as_secs_f64() * 1000.0,
+        (COLS * ROWS) as f64 / per_run.as_secs_f64() / 1e6
+    );
+}
+
+fn main() {
+    println!("{}x{} tiles, {} runs each", COLS, ROWS, RUNS);
+
+    bench("generate (flat)", || (), |_| generate_flat(0.2));
+    bench("generate (nested)", || (), |_| generate_nested(0.2));
+
+    // A sparse board, so that opening the middle reveals most of it.
+    let start = (COLS / 2, ROWS / 2);
+    bench(
+        "flood fill (flat)",
+        || generate_flat(0.02),
+        |mut board| {
+            let start = board.index_of(start);
+            board.reveal_area(start, &Topology::Square, Neighbourhood::Adjacent)
+        },
+    );
+    bench(
+        "flood fill (nested)",
+        || generate_nested(0.02),
+        |mut board| flood_fill_nested(&mut board, start),
+    );
+}
@@ -0,0 +1,135 @@
+use rand::seq::SliceRandom;
+
+use crate::{
+    board::{Board, MineDistributionError, Tile},
+    clock::GameClock,
+    game::{Action, GameContainer, GameMode, GameState},
+    solver::{self, Rules},
+};
+
+/// The most actions that can be undone in a row. Older states of the board are forgotten.
+const UNDO_LIMIT: usize = 100;
+
+/// The state of a game before an action, which undoing the action returns to.
+pub(crate) struct Snapshot {
+    board: Board,
+    tiles_revealed: usize,
+    tiles_flagged: isize,
+    mine_tiles: usize,
+    state: GameState,
+    exploded: Option<usize>,
+    /// The length of the action log before the action.
+    log_len: usize,
+}
+
+impl GameContainer {
+    /// Whether the player may restart, undo and ask for hints. These are only for games played alone,
+    /// since they would be unfair in games against or watched by others, and could be used to study a daily challenge.
+    fn assists_allowed(&self) -> bool {
+        matches!(self.mode, GameMode::Classic)
+    }
+
+    /// The state of the board before an action, if the action could be undone.
+    pub(crate) fn snapshot(&self) -> Option<Snapshot> {
+        if !self.assists_allowed() {
+            return None;
+        }
+        Some(Snapshot {
+            board: self.board.clone(),
+            tiles_revealed: self.tiles_revealed,
+            tiles_flagged: self.tiles_flagged,
+            mine_tiles: self.mine_tiles,
+            state: self.state,
+            exploded: self.exploded,
+            log_len: self.log.len(),
+        })
+    }
+
+    /// Keeps the state of the board from before an action, unless the action didn't change anything.
+    pub(crate) fn keep_snapshot(&mut self, snapshot: Option<Snapshot>) {
+        if let Some(snapshot) = snapshot {
+            if snapshot.board != self.board {
+                if self.history.len() == UNDO_LIMIT {
+                    self.history.remove(0);
+                }
+                self.history.push(snapshot);
+            }
+        }
+    }
+
+    /// Takes back the latest action that changed the board, including the one that hit a mine.
+    /// The clock keeps running, so undoing doesn't give back the time that was spent.
+    pub(crate) fn undo(&mut self) {
+        if self.is_paused() || !self.assists_allowed() {
+            return;
+        }
+        let snapshot = match self.history.pop() {
+            Some(snapshot) => snapshot,
+            None => return,
+        };
+        self.board = snapshot.board;
+        self.tiles_revealed = snapshot.tiles_revealed;
+        self.tiles_flagged = snapshot.tiles_flagged;
+        self.mine_tiles = snapshot.mine_tiles;
+        self.log.truncate(snapshot.log_len);
+        if self.state != GameState::Playing && snapshot.state == GameState::Playing {
+            self.stats.final_time = None;
+            self.clock.reopen();
+        }
+        self.state = snapshot.state;
+        self.exploded = snapshot.exploded;
+        self.no_deduction = false;
+    }
+
+    /// Starts the game over on an empty board of the same shape. A seeded game gets the same mines again,
+    /// if it is opened at the same tile.
+    pub(crate) fn restart(&mut self) {
+        if !self.assists_allowed() {
+            return;
+        }
+        for tile in 0..self.board.len() {
+            let active = self.board[tile].is_active();
+            self.board[tile] = Tile::default();
+            self.board[tile].set_active(active);
+        }
+        self.tiles_revealed = 0;
+        self.tiles_flagged = 0;
+        self.mine_tiles = 0;
+        self.clock = GameClock::default();
+        self.state = GameState::Playing;
+        self.exploded = None;
+        self.stats.final_time = None;
+        self.log.clear();
+        self.history.clear();
+        self.no_deduction = false;
+    }
+
+    /// Reveals a tile that the revealed numbers show to be safe, as the player could have worked out.
+    /// In a liar game, any number could be the lie of its region. If nothing can be deduced, the player is told so.
+    /// There are no hints before the first click, since the mines haven't been placed yet.
+    pub(crate) fn hint(&mut self) -> Result<(), MineDistributionError> {
+        if self.is_paused()
+            || !self.assists_allowed()
+            || self.state != GameState::Playing
+            || self.tiles_revealed == 0
+        {
+            return Ok(());
+        }
+        let safe = solver::safe_tiles(
+            &self.board,
+            Rules {
+                topology: &self.topology,
+                neighbourhood: self.neighbourhood,
+                max_mines_per_tile: self.max_mines_per_tile,
+                liar_region: self.liar_region,
+            },
+        );
+        match safe.choose(&mut rand::thread_rng()) {
+            Some(&tile) => self.act(Action::Reveal(tile)),
+            None => {
+                self.no_deduction = true;
+                Ok(())
+            }
+        }
+    }
+}
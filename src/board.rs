@@ -0,0 +1,570 @@
+use std::{
+    fmt,
+    ops::{Index, IndexMut},
+};
+
+use rand::{seq::SliceRandom, Rng};
+
+use crate::topology::{Neighbourhood, Topology};
+
+/// The mark a player has put on a hidden tile.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Mark {
+    None,
+    Flag,
+    Question,
+}
+
+/// Game tile, packed into two bytes.
+///
+/// Bits 0-6 hold the amount of surrounding mines, bits 7-8 the amount of mines on the tile itself (0-3),
+/// bit 9 is set for revealed tiles and bit 10 for disabled tiles (holes in the board).
+/// Bits 11-12 hold the amount of flags on the tile (0-3) and bit 13 is set for question marks.
+/// Bit 14 is set for tiles that show a wrong number, which is one more than the real number, or one less if bit 15 is set.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Tile(u16);
+
+impl Tile {
+    const NUMBER: u16 = 0b111_1111;
+    const MINES: u16 = 0b11 << 7;
+    const MINES_SHIFT: u16 = 7;
+    const REVEALED: u16 = 1 << 9;
+    const DISABLED: u16 = 1 << 10;
+    const FLAGS: u16 = 0b11 << 11;
+    const FLAGS_SHIFT: u16 = 11;
+    const QUESTION: u16 = 1 << 13;
+    const LIE: u16 = 1 << 14;
+    const LIE_DOWN: u16 = 1 << 15;
+
+    /// The largest amount of mines a single tile can hold.
+    pub const MAX_MINES: u8 = 3;
+    /// The largest number a tile can show, which is the most mines its neighbours may hold between them.
+    pub const MAX_NUMBER: u8 = Tile::NUMBER as u8;
+
+    pub fn is_a_mine(self) -> bool {
+        self.mines() > 0
+    }
+
+    /// The amount of mines on the tile. Always 0 or 1, unless more mines per tile are allowed.
+    pub fn mines(self) -> u8 {
+        ((self.0 & Tile::MINES) >> Tile::MINES_SHIFT) as u8
+    }
+
+    pub fn set_mines(&mut self, mines: u8) {
+        debug_assert!(mines <= Tile::MAX_MINES);
+        self.0 = (self.0 & !Tile::MINES) | ((mines as u16) << Tile::MINES_SHIFT & Tile::MINES);
+    }
+
+    /// Whether the tile is part of the board. Disabled tiles are holes in the board, which are never mines
+    /// and never neighbour any other tile.
+    pub fn is_active(self) -> bool {
+        self.0 & Tile::DISABLED == 0
+    }
+
+    pub fn set_active(&mut self, is_active: bool) {
+        self.set_bit(Tile::DISABLED, !is_active);
+    }
+
+    pub fn is_revealed(self) -> bool {
+        self.0 & Tile::REVEALED != 0
+    }
+
+    pub fn set_revealed(&mut self, is_revealed: bool) {
+        self.set_bit(Tile::REVEALED, is_revealed);
+    }
+
+    pub fn mark(self) -> Mark {
+        if self.flags() > 0 {
+            Mark::Flag
+        } else if self.0 & Tile::QUESTION != 0 {
+            Mark::Question
+        } else {
+            Mark::None
+        }
+    }
+
+    /// Sets the mark of the tile. `Mark::Flag` puts a single flag on the tile.
+    pub fn set_mark(&mut self, mark: Mark) {
+        self.set_flags(if mark == Mark::Flag { 1 } else { 0 });
+        self.set_bit(Tile::QUESTION, mark == Mark::Question);
+    }
+
+    /// Whether the tile is marked with a flag. Question marks don't count as flags.
+    pub fn is_flagged(self) -> bool {
+        self.flags() > 0
+    }
+
+    /// The amount of flags stacked on the tile.
+    pub fn flags(self) -> u8 {
+        ((self.0 & Tile::FLAGS) >> Tile::FLAGS_SHIFT) as u8
+    }
+
+    pub fn set_flags(&mut self, flags: u8) {
+        debug_assert!(flags <= Tile::MAX_MINES);
+        self.0 = (self.0 & !Tile::FLAGS) | ((flags as u16) << Tile::FLAGS_SHIFT & Tile::FLAGS);
+        if flags > 0 {
+            self.set_bit(Tile::QUESTION, false);
+        }
+    }
+
+    /// The amount of mines surrounding the tile, or `None` if there are none or if the tile is a mine.
+    pub fn number(self) -> Option<u8> {
+        match self.0 & Tile::NUMBER {
+            _ if self.is_a_mine() => None,
+            0 => None,
+            n => Some(n as u8),
+        }
+    }
+
+    /// The number shown to the player, which is off by one for lying tiles.
+    pub fn displayed_number(self) -> Option<u8> {
+        match (self.number(), self.lie()) {
+            (number, 0) => number,
+            (number, lie) => {
+                Some((number.unwrap_or(0) as i16 + lie as i16) as u8).filter(|&n| n > 0)
+            }
+        }
+    }
+
+    /// How much the shown number differs from the real number: -1, 0 or 1.
+    pub fn lie(self) -> i8 {
+        match (self.0 & Tile::LIE != 0, self.0 & Tile::LIE_DOWN != 0) {
+            (false, _) => 0,
+            (true, false) => 1,
+            (true, true) => -1,
+        }
+    }
+
+    pub fn set_lie(&mut self, lie: i8) {
+        debug_assert!((-1..=1).contains(&lie));
+        self.set_bit(Tile::LIE, lie != 0);
+        self.set_bit(Tile::LIE_DOWN, lie < 0);
+    }
+
+    pub fn set_number(&mut self, number: Option<u8>) {
+        let n = number.unwrap_or(0) as u16;
+        debug_assert!(
+            n <= Tile::NUMBER,
+            "tile number {} does not fit in a tile",
+            n
+        );
+        self.0 = (self.0 & !Tile::NUMBER) | (n & Tile::NUMBER);
+    }
+
+    /// The raw bits of the tile, as sent over the network.
+    pub fn bits(self) -> u16 {
+        self.0
+    }
+
+    pub fn from_bits(bits: u16) -> Tile {
+        Tile(bits)
+    }
+
+    /// The tile as the player may see it: the mines and number of a hidden tile are left out.
+    pub fn public(self) -> Tile {
+        if self.is_revealed() {
+            self
+        } else {
+            Tile(self.0 & !(Tile::NUMBER | Tile::MINES | Tile::LIE | Tile::LIE_DOWN))
+        }
+    }
+
+    fn set_bit(&mut self, bit: u16, value: bool) {
+        if value {
+            self.0 |= bit;
+        } else {
+            self.0 &= !bit;
+        }
+    }
+}
+
+/// Errors that can occur when distributing the mines on the board.
+#[derive(Debug)]
+pub enum MineDistributionError {
+    /// More mines were requested than there are tiles that may hold them.
+    TooManyMines { requested: usize, available: usize },
+}
+impl fmt::Display for MineDistributionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MineDistributionError::TooManyMines {
+                requested,
+                available,
+            } => write!(
+                f,
+                "Cannot place {} mines, only {} tiles are available for mines.",
+                requested, available
+            ),
+        }
+    }
+}
+impl std::error::Error for MineDistributionError {}
+
+/// Flat, row-major storage of the game tiles.
+///
+/// The tile at coordinates (x, y) is stored at index `y * stride + x`, where the stride is the amount of columns.
+/// Tiles are accessed by their index, i.e. `board[board.index_of((x, y))]`.
+///
+/// A three-dimensional board stores its layers one after another, so the tile at (x, y, z)
+/// is stored at index `(z * rows + y) * stride + x`. A flat board has a single layer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Board {
+    tiles: Vec<Tile>,
+    stride: usize,
+    rows: usize,
+    layers: usize,
+}
+
+impl Board {
+    /// Creates a board of uninitialized tiles with the given dimensions.
+    pub fn new(cols: usize, rows: usize) -> Board {
+        Board::with_layers(cols, rows, 1)
+    }
+
+    /// Creates a three-dimensional board of uninitialized tiles, with `layers` layers of `cols` x `rows` tiles.
+    pub fn with_layers(cols: usize, rows: usize, layers: usize) -> Board {
+        Board {
+            tiles: vec![Tile::default(); cols * rows * layers],
+            stride: cols,
+            rows,
+            layers,
+        }
+    }
+
+    pub fn cols(&self) -> usize {
+        self.stride
+    }
+
+    /// The amount of rows of each layer.
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn layers(&self) -> usize {
+        self.layers
+    }
+
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+
+    /// The amount of tiles that are part of the board, i.e. not disabled.
+    pub fn active_tiles(&self) -> usize {
+        self.tiles.iter().filter(|tile| tile.is_active()).count()
+    }
+
+    /// Converts coordinates into an index of the underlying storage.
+    pub fn index_of(&self, (x, y): (usize, usize)) -> usize {
+        debug_assert!(x < self.stride && y < self.rows);
+        y * self.stride + x
+    }
+
+    /// Converts an index of the underlying storage back into coordinates.
+    pub fn coords_of(&self, index: usize) -> (usize, usize) {
+        (index % self.stride, index / self.stride)
+    }
+
+    /// Converts coordinates of a three-dimensional board into an index of the underlying storage.
+    pub fn index_of_3d(&self, (x, y, z): (usize, usize, usize)) -> usize {
+        debug_assert!(x < self.stride && y < self.rows && z < self.layers);
+        (z * self.rows + y) * self.stride + x
+    }
+
+    /// Converts an index of the underlying storage back into coordinates of a three-dimensional board.
+    pub fn coords_of_3d(&self, index: usize) -> (usize, usize, usize) {
+        let (x, y) = self.coords_of(index);
+        (x, y % self.rows, y / self.rows)
+    }
+
+    /// Places mines on `mines` of the given slots, picked uniformly at random with a partial Fisher-Yates shuffle.
+    ///
+    /// Each slot is the index of a tile. A tile that may hold several mines is given as several slots,
+    /// so that the mines are spread uniformly over the slots. Returns the amount of tiles that got their first mine.
+    pub fn place_mines<R: Rng + ?Sized>(
+        &mut self,
+        slots: &mut [usize],
+        mines: usize,
+        rng: &mut R,
+    ) -> Result<usize, MineDistributionError> {
+        if mines > slots.len() {
+            return Err(MineDistributionError::TooManyMines {
+                requested: mines,
+                available: slots.len(),
+            });
+        }
+        let (mine_slots, _) = slots.partial_shuffle(rng, mines);
+        let mut mine_tiles = 0;
+        for &tile in mine_slots.iter() {
+            let tile_mines = self[tile].mines();
+            if tile_mines == 0 {
+                mine_tiles += 1;
+            }
+            self[tile].set_mines(tile_mines + 1);
+        }
+        Ok(mine_tiles)
+    }
+
+    /// Adds the mines of every tile to the numbers of its neighbours that aren't mines.
+    /// Disabled tiles are never neighbours.
+    pub fn set_numbers(&mut self, topology: &Topology, neighbourhood: Neighbourhood) {
+        for tile_index in 0..self.len() {
+            let mines = self[tile_index].mines();
+            if mines == 0 {
+                continue;
+            }
+            for neighbour in topology.neighbours(self, tile_index, neighbourhood) {
+                let tile = &mut self[neighbour];
+                if tile.is_active() && !tile.is_a_mine() {
+                    tile.set_number(Some(tile.number().unwrap_or(0) + mines));
+                }
+            }
+        }
+    }
+
+    /// Reveals the given tile, and keeps revealing the neighbours of every revealed tile without a number,
+    /// like opening an empty tile. Mines, flagged tiles and disabled tiles are left hidden.
+    /// Returns the amount of tiles that were revealed.
+    ///
+    /// Iterative, since an opening on a large board can be far deeper than the stack allows for recursion.
+    pub fn reveal_area(
+        &mut self,
+        start: usize,
+        topology: &Topology,
+        neighbourhood: Neighbourhood,
+    ) -> usize {
+        let mut revealed = 0;
+        let mut stack = vec![start];
+        while let Some(tile_index) = stack.pop() {
+            let tile = &mut self[tile_index];
+            if !tile.is_active() || tile.is_revealed() || tile.is_flagged() || tile.is_a_mine() {
+                continue;
+            }
+            tile.set_revealed(true);
+            revealed += 1;
+            if tile.number().is_none() {
+                stack.extend(topology.neighbours(self, tile_index, neighbourhood));
+            }
+        }
+        revealed
+    }
+
+    /// The 3BV of the board: the amount of openings, plus the safe tiles that no opening reveals.
+    /// Clearing the board takes at least this many left-clicks.
+    pub fn three_bv(&self, topology: &Topology, neighbourhood: Neighbourhood) -> usize {
+        let safe = |tile: usize| self[tile].is_active() && !self[tile].is_a_mine();
+        let mut counted = vec![false; self.len()];
+        let mut three_bv = 0;
+
+        for tile in 0..self.len() {
+            if counted[tile] || !safe(tile) || self[tile].number().is_some() {
+                continue;
+            }
+            // An opening, together with the numbers around it.
+            three_bv += 1;
+            counted[tile] = true;
+            let mut to_visit = vec![tile];
+            while let Some(empty) = to_visit.pop() {
+                for neighbour in topology.neighbours(self, empty, neighbourhood) {
+                    if counted[neighbour] || !safe(neighbour) {
+                        continue;
+                    }
+                    counted[neighbour] = true;
+                    if self[neighbour].number().is_none() {
+                        to_visit.push(neighbour);
+                    }
+                }
+            }
+        }
+        three_bv
+            + (0..self.len())
+                .filter(|&tile| !counted[tile] && safe(tile))
+                .count()
+    }
+
+    /// Iterates over all tiles in row-major order, together with their indices.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, Tile)> + '_ {
+        self.tiles.iter().copied().enumerate()
+    }
+}
+
+impl Index<usize> for Board {
+    type Output = Tile;
+
+    fn index(&self, index: usize) -> &Tile {
+        &self.tiles[index]
+    }
+}
+
+impl IndexMut<usize> for Board {
+    fn index_mut(&mut self, index: usize) -> &mut Tile {
+        &mut self.tiles[index]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    const RUNS: usize = 20_000;
+
+    /// Pearson's chi-squared statistic of the observed counts, against the same expected count for each of them.
+    fn chi_squared(observed: &[usize]) -> f64 {
+        let expected = observed.iter().sum::<usize>() as f64 / observed.len() as f64;
+        observed
+            .iter()
+            .map(|&count| (count as f64 - expected).powi(2) / expected)
+            .sum()
+    }
+
+    #[test]
+    fn mines_are_placed_uniformly() {
+        let mut rng = StdRng::seed_from_u64(27);
+        let protected = [0, 1, 9, 10];
+        let mut counts = [0; 81];
+        for _ in 0..RUNS {
+            let mut board = Board::new(9, 9);
+            let mut slots: Vec<usize> = (0..81).filter(|tile| !protected.contains(tile)).collect();
+            assert_eq!(board.place_mines(&mut slots, 10, &mut rng).unwrap(), 10);
+            for (tile, count) in counts.iter_mut().enumerate() {
+                *count += board[tile].mines() as usize;
+            }
+        }
+
+        for &tile in protected.iter() {
+            assert_eq!(counts[tile], 0, "protected tile {} got a mine", tile);
+        }
+        let eligible: Vec<usize> = (0..81)
+            .filter(|tile| !protected.contains(tile))
+            .map(|tile| counts[tile])
+            .collect();
+        assert_eq!(eligible.iter().sum::<usize>(), RUNS * 10);
+        // The critical value for 76 degrees of freedom at a significance level of 0.001.
+        let statistic = chi_squared(&eligible);
+        assert!(
+            statistic < 119.85,
+            "chi-squared statistic {} is too large",
+            statistic
+        );
+    }
+
+    #[test]
+    fn stacked_mines_are_spread_over_the_slots() {
+        let mut rng = StdRng::seed_from_u64(36);
+        let mut counts = [0; 25];
+        for _ in 0..RUNS {
+            let mut board = Board::new(5, 5);
+            let mut slots: Vec<usize> = (0..25).flat_map(|tile| vec![tile; 3]).collect();
+            let mine_tiles = board.place_mines(&mut slots, 30, &mut rng).unwrap();
+            assert_eq!(
+                mine_tiles,
+                board.iter().filter(|(_, tile)| tile.is_a_mine()).count()
+            );
+            for (tile, count) in counts.iter_mut().enumerate() {
+                assert!(board[tile].mines() <= Tile::MAX_MINES);
+                *count += board[tile].mines() as usize;
+            }
+        }
+
+        // The critical value for 24 degrees of freedom at a significance level of 0.001.
+        let statistic = chi_squared(&counts);
+        assert!(
+            statistic < 51.18,
+            "chi-squared statistic {} is too large",
+            statistic
+        );
+    }
+
+    #[test]
+    fn the_largest_number_can_lie() {
+        let mut tile = Tile::default();
+        tile.set_number(Some(Tile::MAX_NUMBER));
+        tile.set_lie(1);
+        assert_eq!(tile.displayed_number(), Some(Tile::MAX_NUMBER + 1));
+        tile.set_lie(-1);
+        assert_eq!(tile.displayed_number(), Some(Tile::MAX_NUMBER - 1));
+    }
+
+    #[test]
+    fn too_many_mines_are_rejected() {
+        let mut board = Board::new(3, 3);
+        let mut slots: Vec<usize> = (1..9).collect();
+        let result = board.place_mines(&mut slots, 9, &mut StdRng::seed_from_u64(0));
+        assert!(matches!(
+            result,
+            Err(MineDistributionError::TooManyMines {
+                requested: 9,
+                available: 8
+            })
+        ));
+        assert!(board.iter().all(|(_, tile)| !tile.is_a_mine()));
+    }
+
+    #[test]
+    fn numbers_count_the_neighbouring_mines() {
+        // 2 . .
+        // . . .
+        // . . 1
+        let mut board = Board::new(3, 3);
+        board[0].set_mines(2);
+        board[8].set_mines(1);
+        board.set_numbers(&Topology::Square, Neighbourhood::Adjacent);
+        let numbers: Vec<Option<u8>> = board.iter().map(|(_, tile)| tile.number()).collect();
+        assert_eq!(numbers[..3], [None, Some(2), None]);
+        assert_eq!(numbers[3..6], [Some(2), Some(3), Some(1)]);
+        assert_eq!(numbers[6..], [None, Some(1), None]);
+    }
+
+    #[test]
+    fn opening_stops_at_numbers_and_flags() {
+        // . . . . *
+        // . . . . .
+        // F . . . .
+        let mut board = Board::new(5, 3);
+        board[4].set_mines(1);
+        board[10].set_mark(Mark::Flag);
+        board.set_numbers(&Topology::Square, Neighbourhood::Adjacent);
+        let revealed = board.reveal_area(0, &Topology::Square, Neighbourhood::Adjacent);
+        assert_eq!(revealed, 13);
+        assert!(!board[4].is_revealed() && !board[10].is_revealed());
+        assert!(board[3].is_revealed() && board[3].number() == Some(1));
+    }
+
+    #[test]
+    fn three_bv_counts_openings_and_lone_numbers() {
+        // A mine in the middle of a 5x3 board: an opening on either side, and a 1 above and below the mine
+        // that neither opening reaches.
+        let mut board = Board::new(5, 3);
+        let mine = board.index_of((2, 1));
+        board[mine].set_mines(1);
+        board.set_numbers(&Topology::Square, Neighbourhood::Adjacent);
+        assert_eq!(
+            board.three_bv(&Topology::Square, Neighbourhood::Adjacent),
+            4
+        );
+
+        // A mine in the corner leaves a single opening that reaches every number.
+        let mut board = Board::new(3, 3);
+        board[0].set_mines(1);
+        board.set_numbers(&Topology::Square, Neighbourhood::Adjacent);
+        assert_eq!(
+            board.three_bv(&Topology::Square, Neighbourhood::Adjacent),
+            1
+        );
+    }
+
+    #[test]
+    fn a_huge_opening_does_not_overflow_the_stack() {
+        let mut board = Board::new(1000, 1000);
+        board[0].set_mines(1);
+        board.set_numbers(&Topology::Square, Neighbourhood::Adjacent);
+        let start = board.index_of((500, 500));
+        let revealed = board.reveal_area(start, &Topology::Square, Neighbourhood::Adjacent);
+        assert_eq!(revealed, 1000 * 1000 - 1);
+    }
+}
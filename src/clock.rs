@@ -0,0 +1,86 @@
+use std::time::{Duration, Instant};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ClockState {
+    Idle,
+    Running { since: Instant },
+    Paused,
+    Stopped,
+}
+
+/// Monotonic game clock, which can be paused and resumed.
+///
+/// Built on `Instant`, so adjustments of the system clock can't make it fail or jump.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct GameClock {
+    state: ClockState,
+    /// The time counted before the clock was last resumed.
+    accumulated: Duration,
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        GameClock {
+            state: ClockState::Idle,
+            accumulated: Duration::from_secs(0),
+        }
+    }
+}
+
+impl GameClock {
+    /// Starts the clock, unless it has already been started.
+    pub(crate) fn start(&mut self) {
+        if self.state == ClockState::Idle {
+            self.state = ClockState::Running {
+                since: Instant::now(),
+            };
+        }
+    }
+
+    pub(crate) fn pause(&mut self) {
+        if let ClockState::Running { since } = self.state {
+            self.accumulated += since.elapsed();
+            self.state = ClockState::Paused;
+        }
+    }
+
+    pub(crate) fn resume(&mut self) {
+        if self.state == ClockState::Paused {
+            self.state = ClockState::Running {
+                since: Instant::now(),
+            };
+        }
+    }
+
+    /// Stops the clock for good and returns the final time.
+    pub(crate) fn stop(&mut self) -> Duration {
+        self.pause();
+        self.state = ClockState::Stopped;
+        self.accumulated
+    }
+
+    /// Lets a stopped clock run on from its final time, for when a finished game is taken back.
+    pub(crate) fn reopen(&mut self) {
+        if self.state == ClockState::Stopped {
+            self.state = ClockState::Paused;
+            self.resume();
+        }
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.state == ClockState::Paused
+    }
+
+    /// The time the clock has been running for, not counting the time it has been paused.
+    pub(crate) fn elapsed(&self) -> Duration {
+        match self.state {
+            ClockState::Running { since } => self.accumulated + since.elapsed(),
+            _ => self.accumulated,
+        }
+    }
+}
+
+/// Formats a duration with millisecond precision, e.g. `12.345s`.
+pub(crate) fn format_duration(duration: Duration) -> String {
+    format!("{}.{:03}s", duration.as_secs(), duration.subsec_millis())
+}
@@ -0,0 +1,335 @@
+use std::fmt;
+
+use crate::{
+    game::{FirstClickPolicy, GameSettings},
+    topology::{Neighbourhood, Topology},
+};
+
+/// The version of the game codes. Must be bumped whenever a change to the game makes the same code give a different
+/// game, e.g. a change to how the mines are distributed, so that old codes are rejected instead of giving another board.
+///
+/// The version only covers the game's own code. Seeds are hashed and expanded portably (see `seed`), but the mines
+/// are placed with rand's shuffling, whose results rand only keeps stable within a release, so upgrading rand can
+/// change the board of a code without this version noticing.
+const GAME_CODE_VERSION: u32 = 2;
+
+/// Errors that can occur when reading a game code.
+#[derive(Copy, Clone, Debug)]
+pub(crate) enum GameCodeError {
+    /// The text isn't a game code at all.
+    NotACode,
+    /// The code was made by a version of the game that generates boards differently.
+    IncompatibleVersion(u32),
+    /// The code has the right version, but some part of it can't be read.
+    Invalid(&'static str),
+}
+
+impl fmt::Display for GameCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameCodeError::NotACode => write!(f, "This is not a game code. Game codes start with \"RS\"."),
+            GameCodeError::IncompatibleVersion(version) => write!(
+                f,
+                "This code is for version {} of the game codes, but this game only plays version {}.",
+                version, GAME_CODE_VERSION
+            ),
+            GameCodeError::Invalid(part) => write!(f, "Invalid {} in the game code.", part),
+        }
+    }
+}
+
+impl std::error::Error for GameCodeError {}
+
+impl GameSettings {
+    /// A code for sharing the game: `RS<version>-<shape><cols>x<rows>[x<layers>]-<mines>-<rules>-<seed>`.
+    ///
+    /// The shape is a letter, and the rules are five digits: the first click policy, the most mines per tile,
+    /// question marks, the size of the liar regions (0 if numbers never lie) and the neighbourhood.
+    /// Returns `None` for games that can't be described by a code, i.e. unseeded games and games on loaded boards.
+    pub(crate) fn to_code(&self) -> Option<String> {
+        let shape = match self.topology {
+            Topology::Square => "S".to_string(),
+            Topology::Torus => "T".to_string(),
+            Topology::Hex => "H".to_string(),
+            Topology::Triangle => "R".to_string(),
+            Topology::Cube { layers } => format!("C{}x", layers),
+            Topology::Graph { .. } => return None,
+        };
+        if self.mask.is_some() {
+            return None;
+        }
+        let rules = format!(
+            "{}{}{}{}{}",
+            match self.first_click_policy {
+                FirstClickPolicy::Unprotected => 0,
+                FirstClickPolicy::SafeTile => 1,
+                FirstClickPolicy::Opening => 2,
+                FirstClickPolicy::SafeArea => 3,
+            },
+            self.max_mines_per_tile,
+            self.question_marks as u8,
+            self.liar_region.unwrap_or(0),
+            match self.neighbourhood {
+                Neighbourhood::Adjacent => 0,
+                Neighbourhood::Knight => 1,
+                Neighbourhood::Radius2 => 2,
+            }
+        );
+        Some(format!(
+            "RS{}-{}{}x{}-{}-{}-{}",
+            GAME_CODE_VERSION,
+            shape,
+            self.cols,
+            self.rows,
+            self.mines,
+            rules,
+            self.seed.as_ref()?
+        ))
+    }
+
+    /// Reads the settings of a game from its code, see `GameSettings::to_code`.
+    pub(crate) fn from_code(code: &str) -> Result<GameSettings, GameCodeError> {
+        let code = code.trim();
+        if !code.starts_with("RS") {
+            return Err(GameCodeError::NotACode);
+        }
+        let mut parts = code[2..].splitn(5, '-');
+        let version = parts
+            .next()
+            .and_then(|version| version.parse().ok())
+            .ok_or(GameCodeError::NotACode)?;
+        if version != GAME_CODE_VERSION {
+            return Err(GameCodeError::IncompatibleVersion(version));
+        }
+        let (shape, mines, rules, seed) =
+            match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some(shape), Some(mines), Some(rules), Some(seed)) if !seed.is_empty() => {
+                    (shape, mines, rules, seed)
+                }
+                _ => return Err(GameCodeError::Invalid("layout")),
+            };
+
+        let (letter, size) = match (shape.get(..1), shape.get(1..)) {
+            (Some(letter), Some(size)) => (letter, size),
+            _ => return Err(GameCodeError::Invalid("board shape")),
+        };
+        let size: Vec<usize> = size
+            .split('x')
+            .map(|number| {
+                number
+                    .parse()
+                    .map_err(|_| GameCodeError::Invalid("board size"))
+            })
+            .collect::<Result<_, _>>()?;
+        let (topology, cols, rows) = match (letter, &size[..]) {
+            ("S", &[cols, rows]) => (Topology::Square, cols, rows),
+            ("T", &[cols, rows]) => (Topology::Torus, cols, rows),
+            ("H", &[cols, rows]) => (Topology::Hex, cols, rows),
+            ("R", &[cols, rows]) => (Topology::Triangle, cols, rows),
+            ("C", &[layers, cols, rows]) => (Topology::Cube { layers }, cols, rows),
+            _ => return Err(GameCodeError::Invalid("board shape")),
+        };
+        let invalid_rules = GameCodeError::Invalid("rules");
+        let rules: Vec<u32> = rules
+            .chars()
+            .map(|digit| digit.to_digit(10))
+            .collect::<Option<_>>()
+            .ok_or(invalid_rules)?;
+        let (policy, max_mines_per_tile, question_marks, liar_region, neighbourhood) = match rules[..]
+        {
+            [policy, max_mines, question_marks, liar_region, neighbourhood] => (
+                policy,
+                max_mines,
+                question_marks,
+                liar_region,
+                neighbourhood,
+            ),
+            _ => return Err(invalid_rules),
+        };
+        let settings = GameSettings {
+            cols,
+            rows,
+            mines: mines
+                .parse()
+                .map_err(|_| GameCodeError::Invalid("mine count"))?,
+            seed: Some(seed.to_string()),
+            first_click_policy: match policy {
+                0 => FirstClickPolicy::Unprotected,
+                1 => FirstClickPolicy::SafeTile,
+                2 => FirstClickPolicy::Opening,
+                3 => FirstClickPolicy::SafeArea,
+                _ => return Err(invalid_rules),
+            },
+            question_marks: match question_marks {
+                0 => false,
+                1 => true,
+                _ => return Err(invalid_rules),
+            },
+            max_mines_per_tile: max_mines_per_tile as u8,
+            liar_region: match liar_region {
+                0 => None,
+                liar_region => Some(liar_region as usize),
+            },
+            neighbourhood: match neighbourhood {
+                0 => Neighbourhood::Adjacent,
+                1 => Neighbourhood::Knight,
+                2 => Neighbourhood::Radius2,
+                _ => return Err(invalid_rules),
+            },
+            topology,
+            mask: None,
+        };
+        settings.validate(None).map_err(GameCodeError::Invalid)?;
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(topology: Topology) -> GameSettings {
+        let side = match topology {
+            Topology::Cube { layers } => layers,
+            _ => 9,
+        };
+        GameSettings {
+            cols: side,
+            rows: side,
+            mines: 10,
+            seed: Some("a seed - with dashes".to_string()),
+            first_click_policy: FirstClickPolicy::SafeTile,
+            question_marks: false,
+            max_mines_per_tile: 1,
+            liar_region: None,
+            neighbourhood: Neighbourhood::Adjacent,
+            topology,
+            mask: None,
+        }
+    }
+
+    #[test]
+    fn every_game_is_read_back_from_its_code() {
+        let topologies = [
+            Topology::Square,
+            Topology::Torus,
+            Topology::Hex,
+            Topology::Triangle,
+            Topology::Cube { layers: 4 },
+        ];
+        let neighbourhoods = [
+            Neighbourhood::Adjacent,
+            Neighbourhood::Knight,
+            Neighbourhood::Radius2,
+        ];
+        let policies = [
+            FirstClickPolicy::Unprotected,
+            FirstClickPolicy::SafeTile,
+            FirstClickPolicy::Opening,
+            FirstClickPolicy::SafeArea,
+        ];
+        for topology in &topologies {
+            for &neighbourhood in &neighbourhoods {
+                for &first_click_policy in &policies {
+                    for max_mines_per_tile in 1..=3 {
+                        for &liar_region in &[None, Some(3), Some(4), Some(5)] {
+                            for &question_marks in &[false, true] {
+                                let settings = GameSettings {
+                                    neighbourhood,
+                                    first_click_policy,
+                                    max_mines_per_tile,
+                                    liar_region,
+                                    question_marks,
+                                    ..settings(topology.clone())
+                                };
+                                let code = settings.to_code().unwrap();
+                                assert_eq!(
+                                    GameSettings::from_code(&code).unwrap(),
+                                    settings,
+                                    "{}",
+                                    code
+                                );
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn games_without_a_code_have_none() {
+        let unseeded = GameSettings {
+            seed: None,
+            ..settings(Topology::Square)
+        };
+        assert_eq!(unseeded.to_code(), None);
+    }
+
+    #[test]
+    fn codes_of_impossible_games_are_rejected() {
+        for code in &[
+            "RS2-S0x9-10-11001-seed",
+            "RS2-S9x0-10-11001-seed",
+            "RS2-C0x9x9-10-11001-seed",
+            "RS2-S501x9-10-11001-seed",
+            "RS2-C500x500x500-10-11001-seed",
+            "RS2-S9x9-0-11001-seed",
+            // 80 tiles are left around a safe tile, and each holds one mine.
+            "RS2-S9x9-81-11001-seed",
+            "RS2-S9x9-10-14001-seed",
+            "RS2-S9x9-10-11061-seed",
+        ] {
+            assert!(
+                matches!(
+                    GameSettings::from_code(code),
+                    Err(GameCodeError::Invalid(_))
+                ),
+                "{}",
+                code
+            );
+        }
+        assert!(GameSettings::from_code("RS2-S9x9-80-11001-seed").is_ok());
+    }
+
+    #[test]
+    fn malformed_codes_are_rejected() {
+        assert!(matches!(
+            GameSettings::from_code("hello"),
+            Err(GameCodeError::NotACode)
+        ));
+        assert!(matches!(
+            GameSettings::from_code("RSx-S9x9-10-11001-seed"),
+            Err(GameCodeError::NotACode)
+        ));
+        assert!(matches!(
+            GameSettings::from_code("RS1-S9x9-10-11001-seed"),
+            Err(GameCodeError::IncompatibleVersion(1))
+        ));
+        for code in &[
+            "RS2",
+            "RS2-S9x9-10-11001",
+            "RS2-S9x9-10-11001-",
+            "RS2-Q9x9-10-11001-seed",
+            "RS2-S9-10-11001-seed",
+            "RS2-S9x9x9-10-11001-seed",
+            "RS2-Sninex9-10-11001-seed",
+            "RS2-S9x9-ten-11001-seed",
+            "RS2-S9x9-10-1100-seed",
+            "RS2-S9x9-10-110010-seed",
+            "RS2-S9x9-10-1100a-seed",
+            "RS2-S9x9-10-51001-seed",
+            "RS2-S9x9-10-11201-seed",
+            "RS2-S9x9-10-11003-seed",
+        ] {
+            assert!(
+                matches!(
+                    GameSettings::from_code(code),
+                    Err(GameCodeError::Invalid(_))
+                ),
+                "{}",
+                code
+            );
+        }
+    }
+}
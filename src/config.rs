@@ -0,0 +1,422 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use directories::ProjectDirs;
+use ggez::{
+    event::{KeyCode, MouseButton},
+    input::mouse,
+    Context,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::DEFAULT_TILE_SIZE;
+
+/// The keys that can be bound, by the names they have in the config file.
+const KEYS: [KeyCode; 61] = [
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::Escape,
+    KeyCode::Space,
+    KeyCode::Return,
+    KeyCode::Back,
+    KeyCode::Tab,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::LShift,
+    KeyCode::LControl,
+];
+
+/// The key with the given name, e.g. `P`, `Key1` or `Escape`. Case doesn't matter.
+pub(crate) fn parse_key(name: &str) -> Option<KeyCode> {
+    KEYS.iter()
+        .copied()
+        .find(|key| format!("{:?}", key).eq_ignore_ascii_case(name.trim()))
+}
+
+/// What a key or mouse button does. Revealing, flagging and chording act on the tile under the mouse.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Command {
+    Reveal,
+    Flag,
+    Chord,
+    Restart,
+    Pause,
+    Hint,
+    Undo,
+    Quit,
+    Nothing,
+}
+
+impl Command {
+    /// Every command, in the order they are listed in the settings.
+    pub(crate) const ALL: [Command; 9] = [
+        Command::Reveal,
+        Command::Flag,
+        Command::Chord,
+        Command::Restart,
+        Command::Pause,
+        Command::Hint,
+        Command::Undo,
+        Command::Quit,
+        Command::Nothing,
+    ];
+
+    /// The hidden tiles that are drawn pressed down while this command is held over the given tile:
+    /// the tile itself, along with its `neighbours` when chording. `reveal_chords` is whether revealing the tile
+    /// chords it, and only the tiles that `can_press` are kept.
+    pub(crate) fn pressed_tiles<T: Copy>(
+        self,
+        tile: T,
+        reveal_chords: bool,
+        neighbours: impl FnOnce() -> Vec<T>,
+        can_press: impl Fn(T) -> bool,
+    ) -> Vec<T> {
+        let mut tiles = match self {
+            Command::Chord => neighbours(),
+            Command::Reveal if reveal_chords => neighbours(),
+            Command::Reveal => Vec::new(),
+            _ => return Vec::new(),
+        };
+        tiles.push(tile);
+        tiles.retain(|&tile| can_press(tile));
+        tiles
+    }
+}
+
+impl std::fmt::Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Command::Reveal => "reveals",
+            Command::Flag => "flags",
+            Command::Chord => "chords",
+            Command::Restart => "restarts the game",
+            Command::Pause => "pauses",
+            Command::Hint => "gives a hint",
+            Command::Undo => "undoes the last move",
+            Command::Quit => "quits",
+            Command::Nothing => "does nothing",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// What each mouse button does. Pressing the left and right buttons together always chords.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct MouseButtons {
+    pub(crate) left: Command,
+    pub(crate) right: Command,
+    pub(crate) middle: Command,
+}
+
+impl Default for MouseButtons {
+    fn default() -> MouseButtons {
+        MouseButtons {
+            left: Command::Reveal,
+            right: Command::Flag,
+            middle: Command::Chord,
+        }
+    }
+}
+
+impl MouseButtons {
+    /// What the given button does.
+    pub(crate) fn command(&self, button: MouseButton) -> Command {
+        match button {
+            MouseButton::Left => self.left,
+            MouseButton::Right => self.right,
+            MouseButton::Middle => self.middle,
+            MouseButton::Other(_) => Command::Nothing,
+        }
+    }
+
+    /// What pressing the given button does. Pressing it while the other one of the left and right buttons is held
+    /// chords.
+    pub(crate) fn pressed_command(&self, ctx: &Context, button: MouseButton) -> Command {
+        match button {
+            MouseButton::Left if mouse::button_pressed(ctx, MouseButton::Right) => Command::Chord,
+            MouseButton::Right if mouse::button_pressed(ctx, MouseButton::Left) => Command::Chord,
+            _ => self.command(button),
+        }
+    }
+
+    /// What the held buttons would do to the tile under the mouse, for drawing the tiles they act on pressed down.
+    /// Holding the left and right buttons together chords, otherwise the first held button that reveals or chords
+    /// counts. Any other command gives `Command::Nothing`.
+    pub(crate) fn held_command(&self, ctx: &Context) -> Command {
+        let held = |button| mouse::button_pressed(ctx, button);
+        if held(MouseButton::Left) && held(MouseButton::Right) {
+            return Command::Chord;
+        }
+        [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+            .iter()
+            .filter(|&&button| held(button))
+            .map(|&button| self.command(button))
+            .find(|&command| command == Command::Reveal || command == Command::Chord)
+            .unwrap_or(Command::Nothing)
+    }
+}
+
+/// The key of each command, by the names `parse_key` understands. An empty name leaves the command without a key.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct KeyBindings {
+    pub(crate) reveal: String,
+    pub(crate) flag: String,
+    pub(crate) chord: String,
+    pub(crate) restart: String,
+    pub(crate) pause: String,
+    pub(crate) hint: String,
+    pub(crate) undo: String,
+    pub(crate) quit: String,
+}
+
+impl Default for KeyBindings {
+    fn default() -> KeyBindings {
+        KeyBindings {
+            reveal: String::new(),
+            flag: String::new(),
+            chord: String::new(),
+            restart: "R".to_string(),
+            pause: "P".to_string(),
+            hint: "H".to_string(),
+            undo: "Z".to_string(),
+            quit: "Escape".to_string(),
+        }
+    }
+}
+
+impl KeyBindings {
+    /// The bindings, by the command they are bound to.
+    pub(crate) fn named(&self) -> Vec<(Command, &String)> {
+        vec![
+            (Command::Reveal, &self.reveal),
+            (Command::Flag, &self.flag),
+            (Command::Chord, &self.chord),
+            (Command::Restart, &self.restart),
+            (Command::Pause, &self.pause),
+            (Command::Hint, &self.hint),
+            (Command::Undo, &self.undo),
+            (Command::Quit, &self.quit),
+        ]
+    }
+
+    /// The bindings, by the command they are bound to, for changing them.
+    pub(crate) fn named_mut(&mut self) -> Vec<(Command, &mut String)> {
+        vec![
+            (Command::Reveal, &mut self.reveal),
+            (Command::Flag, &mut self.flag),
+            (Command::Chord, &mut self.chord),
+            (Command::Restart, &mut self.restart),
+            (Command::Pause, &mut self.pause),
+            (Command::Hint, &mut self.hint),
+            (Command::Undo, &mut self.undo),
+            (Command::Quit, &mut self.quit),
+        ]
+    }
+
+    /// What the given key does. If a key is bound to several commands, the first one in the list wins.
+    pub(crate) fn command(&self, key: KeyCode) -> Command {
+        self.named()
+            .into_iter()
+            .find(|(_, name)| parse_key(name) == Some(key))
+            .map_or(Command::Nothing, |(command, _)| command)
+    }
+}
+
+/// The preferences of the player, kept between launches in `config.toml` in the user's config directory.
+///
+/// Missing entries get their default values, so that config files of older versions keep working.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Config {
+    /// The name of the preset that is selected by default.
+    pub(crate) difficulty: String,
+    /// The columns, rows and mines of the last custom board.
+    pub(crate) custom_size: (usize, usize, usize),
+    /// The name of the theme the game is drawn with.
+    pub(crate) theme: String,
+    /// Whether right-clicks cycle through question marks by default.
+    pub(crate) question_marks: bool,
+    /// The largest size of the tiles on the screen, in pixels. Large boards get smaller tiles to fit the screen.
+    pub(crate) tile_size: f32,
+    /// Where the window was when the last game was closed, `None` to let the system decide.
+    pub(crate) window_position: Option<(f64, f64)>,
+    /// Whether revealing a revealed number chords it, so that one button both reveals and chords.
+    pub(crate) reveal_chords: bool,
+    /// Whether flagging a revealed number flags its hidden neighbours, when the number says they are all mines.
+    pub(crate) flag_chords: bool,
+    // Tables have to come after the plain values, or the config can't be written as TOML.
+    pub(crate) keys: KeyBindings,
+    pub(crate) mouse: MouseButtons,
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            difficulty: "Novice".to_string(),
+            custom_size: (20, 20, 60),
+            theme: "dark".to_string(),
+            question_marks: false,
+            tile_size: DEFAULT_TILE_SIZE,
+            window_position: None,
+            reveal_chords: false,
+            flag_chords: false,
+            keys: KeyBindings::default(),
+            mouse: MouseButtons::default(),
+        }
+    }
+}
+
+impl Config {
+    /// The path of the config file, or `None` if there is no home directory.
+    fn path() -> Option<PathBuf> {
+        ProjectDirs::from("", "", "rustsweeper").map(|dirs| dirs.config_dir().join("config.toml"))
+    }
+
+    /// Loads the config file. A missing file gives the defaults, and a broken file is reported and ignored.
+    pub(crate) fn load() -> Config {
+        match Config::path() {
+            Some(path) => Config::load_from(&path),
+            None => Config::default(),
+        }
+    }
+
+    fn load_from(path: &Path) -> Config {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(_) => return Config::default(),
+        };
+        let mut config: Config = match toml::from_str(&contents) {
+            Ok(config) => config,
+            Err(e) => {
+                println!(
+                    "Could not read the config file {}: {}. Using the default settings.",
+                    path.display(),
+                    e
+                );
+                return Config::default();
+            }
+        };
+
+        let defaults = KeyBindings::default();
+        for ((command, key), (_, default)) in
+            config.keys.named_mut().into_iter().zip(defaults.named())
+        {
+            if !key.is_empty() && parse_key(key).is_none() {
+                println!(
+                    "Unknown key \"{}\" in the config file for the key that {}, using \"{}\".",
+                    key, command, default
+                );
+                *key = default.clone();
+            }
+        }
+        if !(8.0..=200.0).contains(&config.tile_size) {
+            config.tile_size = DEFAULT_TILE_SIZE;
+        }
+        config
+    }
+
+    /// Writes the config file, creating the config directory if needed.
+    pub(crate) fn save(&self) -> io::Result<()> {
+        let path = Config::path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No home directory"))?;
+        self.save_to(&path)
+    }
+
+    fn save_to(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let contents =
+            toml::to_string(self).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(path, contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn saved_settings_are_loaded_again() {
+        let config = Config {
+            difficulty: "Expert".to_string(),
+            custom_size: (30, 20, 99),
+            theme: "light".to_string(),
+            question_marks: true,
+            tile_size: 40.0,
+            window_position: Some((10.0, 20.0)),
+            reveal_chords: true,
+            flag_chords: true,
+            keys: KeyBindings {
+                hint: String::new(),
+                ..KeyBindings::default()
+            },
+            mouse: MouseButtons {
+                middle: Command::Flag,
+                ..MouseButtons::default()
+            },
+        };
+        let path = std::env::temp_dir()
+            .join(format!("rustsweeper-test-{}", std::process::id()))
+            .join("config.toml");
+        config.save_to(&path).unwrap();
+        let loaded = Config::load_from(&path);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(loaded, config);
+    }
+}
@@ -0,0 +1,210 @@
+use std::time::{Duration, Instant};
+
+use ggez::graphics::Color;
+
+use crate::{
+    board::{MineDistributionError, Tile},
+    game::{Action, GameContainer, GameMode},
+    net::{Connection, Message},
+};
+
+/// The colours of the players' cursors, by player number.
+pub(crate) const PLAYER_COLOURS: [(u8, u8, u8); 8] = [
+    (230, 60, 60),
+    (60, 140, 230),
+    (60, 200, 90),
+    (230, 200, 40),
+    (190, 90, 220),
+    (240, 140, 40),
+    (40, 210, 210),
+    (230, 110, 170),
+];
+
+/// The colour of the given player's cursor.
+pub(crate) fn player_colour(player: usize) -> Color {
+    let (r, g, b) = PLAYER_COLOURS[player % PLAYER_COLOURS.len()];
+    Color::from_rgb(r, g, b)
+}
+
+/// How often the host sends the state of the game while nothing happens, so that the players' clocks stay in sync.
+const STATE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A co-op game, where all players play on the same board.
+///
+/// The host keeps the board, performs the actions of all players and sends the changed tiles back,
+/// so there is only ever one version of the game. The other players only mirror it.
+pub(crate) struct Coop {
+    pub(crate) connection: Connection,
+    pub(crate) is_host: bool,
+    /// The number of this player. The host is player 0.
+    pub(crate) player: usize,
+    /// The names of the players, by player number.
+    pub(crate) names: Vec<String>,
+    /// The positions of the players' mice, in tiles from the top-left corner of the board.
+    pub(crate) cursors: Vec<Option<(f32, f32)>>,
+    /// The tiles as the other players last saw them. Only kept by the host.
+    sent: Vec<Tile>,
+    /// When the host last sent the state of the game.
+    state_sent: Instant,
+    /// The elapsed time of the host's game as last received, and when it was received.
+    pub(crate) host_elapsed: (Duration, Instant),
+    /// The tile the host opens the board at.
+    start_tile: usize,
+}
+
+impl Coop {
+    /// A co-op game hosted by this player, with the players who joined the lobby, that opens at the given tile.
+    pub(crate) fn host(connection: Connection, names: Vec<String>, start_tile: usize) -> Coop {
+        Coop::new(connection, true, 0, names, start_tile)
+    }
+
+    /// A co-op game hosted by another player, that opens at the given tile.
+    pub(crate) fn join(connection: Connection, player: usize, start_tile: usize) -> Coop {
+        Coop::new(connection, false, player, Vec::new(), start_tile)
+    }
+
+    fn new(
+        connection: Connection,
+        is_host: bool,
+        player: usize,
+        names: Vec<String>,
+        start_tile: usize,
+    ) -> Coop {
+        Coop {
+            connection,
+            is_host,
+            player,
+            cursors: vec![None; names.len()],
+            names,
+            sent: Vec::new(),
+            state_sent: Instant::now(),
+            host_elapsed: (Duration::default(), Instant::now()),
+            start_tile,
+        }
+    }
+
+    pub(crate) fn set_cursor(&mut self, player: usize, position: (f32, f32)) {
+        if self.cursors.len() <= player {
+            self.cursors.resize(player + 1, None);
+        }
+        self.cursors[player] = Some(position);
+    }
+}
+
+impl GameContainer {
+    /// Starts a co-op game. The host tells the players who is playing, and opens the board at the common start tile.
+    pub(crate) fn start_coop(&mut self, mut coop: Coop) -> Result<(), MineDistributionError> {
+        if coop.is_host {
+            for (player, name) in coop.names.clone().into_iter().enumerate() {
+                coop.connection.broadcast(&Message::Player { player, name });
+            }
+            // The players start out with a board of hidden tiles.
+            coop.sent = vec![Tile::default(); self.board.len()];
+            let start_tile = coop.start_tile;
+            self.mode = GameMode::Coop(coop);
+            self.record(0, Action::Reveal(start_tile))?;
+            self.sync_coop();
+        } else {
+            self.mode = GameMode::Coop(coop);
+        }
+        Ok(())
+    }
+
+    /// Sends the tiles that have changed since the last call to the other players, if this player hosts a co-op game.
+    pub(crate) fn sync_coop(&mut self) {
+        let coop = match &mut self.mode {
+            GameMode::Coop(coop) if coop.is_host => coop,
+            _ => return,
+        };
+        let changes: Vec<(usize, Tile)> = self
+            .board
+            .iter()
+            .map(|(tile_index, tile)| (tile_index, tile.public()))
+            .filter(|&(tile_index, tile)| coop.sent[tile_index] != tile)
+            .collect();
+        for &(tile_index, tile) in changes.iter() {
+            coop.sent[tile_index] = tile;
+        }
+
+        if !changes.is_empty() {
+            coop.connection.broadcast(&Message::Tiles(changes));
+        } else if coop.state_sent.elapsed() < STATE_INTERVAL {
+            return;
+        }
+        coop.state_sent = Instant::now();
+        coop.connection.broadcast(&Message::State {
+            state: self.state,
+            flags: self.tiles_flagged,
+            elapsed_ms: self.clock.elapsed().as_millis() as u64,
+        });
+    }
+
+    /// Handles the messages that have arrived from the other players of a co-op game.
+    ///
+    /// The host performs the actions of the players in order of player number, and the actions of each player
+    /// in the order they were taken. Conflicting actions that arrive at the same time, e.g. two players flagging
+    /// the same tile, therefore have the same outcome regardless of network timing.
+    pub(crate) fn poll_coop(&mut self) -> Result<(), MineDistributionError> {
+        let mut coop = match std::mem::replace(&mut self.mode, GameMode::Classic) {
+            GameMode::Coop(coop) => coop,
+            mode => {
+                self.mode = mode;
+                return Ok(());
+            }
+        };
+
+        let mut actions = Vec::new();
+        for (peer, message) in coop.connection.receive() {
+            match message {
+                Message::Action { action, .. } if coop.is_host => actions.push((peer + 1, action)),
+                Message::Cursor { x, y, .. } if coop.is_host => {
+                    coop.set_cursor(peer + 1, (x, y));
+                    coop.connection.broadcast(&Message::Cursor {
+                        player: peer + 1,
+                        x,
+                        y,
+                    });
+                }
+                Message::Cursor { player, x, y } => coop.set_cursor(player, (x, y)),
+                Message::Player { player, name } => {
+                    if coop.names.len() <= player {
+                        coop.names.resize(player + 1, String::new());
+                    }
+                    coop.names[player] = name;
+                }
+                Message::Tiles(changes) => {
+                    for (tile_index, tile) in changes {
+                        if tile_index < self.board.len() {
+                            self.board[tile_index] = tile;
+                        }
+                    }
+                }
+                Message::State {
+                    state,
+                    flags,
+                    elapsed_ms,
+                } => {
+                    self.state = state;
+                    self.tiles_flagged = flags;
+                    coop.host_elapsed = (Duration::from_millis(elapsed_ms), Instant::now());
+                }
+                _ => {}
+            }
+        }
+
+        // A stable sort, so that the actions of each player stay in order.
+        actions.sort_by_key(|&(player, _)| player);
+        let mut result = Ok(());
+        for (player, action) in actions {
+            let tile_index = action.tile();
+            if tile_index >= self.board.len() || !self.board[tile_index].is_active() {
+                continue;
+            }
+            result = result.and(self.record(player, action));
+        }
+
+        self.mode = GameMode::Coop(coop);
+        self.sync_coop();
+        result
+    }
+}
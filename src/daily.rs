@@ -0,0 +1,341 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use directories::ProjectDirs;
+
+use crate::{
+    board::MineDistributionError,
+    clock::format_duration,
+    game::{Action, GameContainer, GameMode, GameState},
+};
+
+/// The most columns and rows of the grid in the summary of a daily challenge.
+const GRID_SIZE: usize = 10;
+
+/// Today's daily challenge, which is the same board for everyone who plays it on the same date.
+///
+/// The board is decided by a seed made from the date and the difficulty, and is opened at its centre tile,
+/// so that the first click doesn't make boards differ between players.
+pub(crate) struct Daily {
+    pub(crate) date: String,
+    pub(crate) difficulty: String,
+    /// The tile the board is opened at.
+    pub(crate) start_tile: usize,
+    /// Whether the result should be stored. Only the first game of the day counts.
+    keep_result: bool,
+}
+
+impl Daily {
+    /// The daily challenge of the given date and difficulty, on a board of the given size.
+    pub(crate) fn new(
+        date: &str,
+        difficulty: &str,
+        cols: usize,
+        rows: usize,
+        keep_result: bool,
+    ) -> Daily {
+        Daily {
+            date: date.to_string(),
+            difficulty: difficulty.to_string(),
+            start_tile: (rows / 2) * cols + cols / 2,
+            keep_result,
+        }
+    }
+
+    /// The seed of the board, which is the same for everyone on the same date.
+    pub(crate) fn seed(&self) -> String {
+        format!("daily {} {}", self.date, self.difficulty)
+    }
+}
+
+/// How a daily challenge ended.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum DailyOutcome {
+    Won,
+    HitAMine,
+    /// Left before it was finished, which counts as a loss.
+    Forfeited,
+}
+
+/// The result of a daily challenge.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct DailyResult {
+    pub(crate) date: String,
+    pub(crate) difficulty: String,
+    pub(crate) outcome: DailyOutcome,
+    pub(crate) time: Duration,
+    /// The 3BV of the board, i.e. the least amount of clicks needed to clear it.
+    pub(crate) three_bv: usize,
+    /// How much of each part of the board was cleared, see `GameContainer::progress_grid`.
+    pub(crate) grid: Vec<String>,
+}
+
+impl DailyResult {
+    /// Parses a line of the results file:
+    /// `<date> <difficulty> <won|lost|forfeited> <time in ms> <3BV> <grid rows, split by />`.
+    fn parse(line: &str) -> Option<DailyResult> {
+        let mut words = line.split_whitespace();
+        let date = words.next()?.to_string();
+        let difficulty = words.next()?.to_string();
+        let outcome = match words.next()? {
+            "won" => DailyOutcome::Won,
+            "lost" => DailyOutcome::HitAMine,
+            "forfeited" => DailyOutcome::Forfeited,
+            _ => return None,
+        };
+        Some(DailyResult {
+            date,
+            difficulty,
+            outcome,
+            time: Duration::from_millis(words.next()?.parse().ok()?),
+            three_bv: words.next()?.parse().ok()?,
+            grid: words.next()?.split('/').map(str::to_string).collect(),
+        })
+    }
+
+    fn to_line(&self) -> String {
+        format!(
+            "{} {} {} {} {} {}\n",
+            self.date,
+            self.difficulty,
+            match self.outcome {
+                DailyOutcome::Won => "won",
+                DailyOutcome::HitAMine => "lost",
+                DailyOutcome::Forfeited => "forfeited",
+            },
+            self.time.as_millis(),
+            self.three_bv,
+            self.grid.join("/")
+        )
+    }
+
+    /// A text summary of the result to share with others. It tells how it went without giving the board away.
+    pub(crate) fn summary(&self) -> String {
+        let outcome = match self.outcome {
+            DailyOutcome::Won => {
+                let three_bv_per_second = self.three_bv as f64 / self.time.as_secs_f64().max(0.001);
+                format!(
+                    "Cleared in {} (3BV {}, {:.2} 3BV/s)",
+                    format_duration(self.time),
+                    self.three_bv,
+                    three_bv_per_second
+                )
+            }
+            DailyOutcome::HitAMine => format!(
+                "Hit a mine after {} (3BV {})",
+                format_duration(self.time),
+                self.three_bv
+            ),
+            DailyOutcome::Forfeited => format!(
+                "Gave up after {} (3BV {})",
+                format_duration(self.time),
+                self.three_bv
+            ),
+        };
+        let mut summary = format!(
+            "Rustsweeper daily {} ({})\n{}\n",
+            self.date, self.difficulty, outcome
+        );
+        for row in self.grid.iter() {
+            summary.push_str(row);
+            summary.push('\n');
+        }
+        summary
+    }
+}
+
+/// Today's date in UTC, as `YYYY-MM-DD`.
+pub(crate) fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64
+        / 86_400;
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// The date of the given day since 1970-01-01, in the proleptic Gregorian calendar.
+/// See http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// The file the results of the daily challenges are stored in, or `None` if there is no home directory.
+fn results_path() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "rustsweeper").map(|dirs| dirs.data_dir().join("daily.txt"))
+}
+
+/// The stored results of earlier daily challenges. Lines that can't be read are skipped.
+pub(crate) fn load_results() -> Vec<DailyResult> {
+    results_path()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .map(|contents| contents.lines().filter_map(DailyResult::parse).collect())
+        .unwrap_or_default()
+}
+
+fn save_result(result: &DailyResult) -> io::Result<()> {
+    let path = results_path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No home directory"))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(result.to_line().as_bytes())
+}
+
+impl GameContainer {
+    /// Starts a daily challenge by opening the board at its start tile, which also starts the clock.
+    pub(crate) fn start_daily(&mut self, daily: Daily) -> Result<(), MineDistributionError> {
+        let start_tile = daily.start_tile;
+        self.mode = GameMode::Daily(daily);
+        self.record(0, Action::Reveal(start_tile))
+    }
+
+    /// Ends a daily challenge that is left before it is finished as a loss, so that the board can't be studied first.
+    pub(crate) fn forfeit_daily(&mut self) {
+        if matches!(self.mode, GameMode::Daily(_)) && self.state == GameState::Playing {
+            self.lose();
+            self.save_daily_result();
+        }
+    }
+
+    /// Stores the result of a finished daily challenge, unless it has been played before today.
+    pub(crate) fn save_daily_result(&mut self) {
+        if self.state == GameState::Playing {
+            return;
+        }
+        let daily = match &mut self.mode {
+            GameMode::Daily(daily) if daily.keep_result => daily,
+            _ => return,
+        };
+        daily.keep_result = false;
+        let result = DailyResult {
+            date: daily.date.clone(),
+            difficulty: daily.difficulty.clone(),
+            outcome: match (self.state, self.exploded) {
+                (GameState::Won, _) => DailyOutcome::Won,
+                (_, Some(_)) => DailyOutcome::HitAMine,
+                (_, None) => DailyOutcome::Forfeited,
+            },
+            time: self.stats.final_time.unwrap_or_default(),
+            three_bv: self.board.three_bv(&self.topology, self.neighbourhood),
+            grid: self.progress_grid(),
+        };
+        if let Err(e) = save_result(&result) {
+            println!("Could not save the result: {}", e);
+        }
+    }
+
+    /// A small picture of how much of the board was cleared, at most `GRID_SIZE` characters across.
+    /// Each character stands for a block of tiles: `#` if all of its safe tiles were revealed, `+` if some were,
+    /// `.` if none were, and `X` for the block of the mine that was hit. A forfeited game has no `X`.
+    fn progress_grid(&self) -> Vec<String> {
+        let block_size = (self.game_cols.max(self.game_rows).max(1) - 1) / GRID_SIZE + 1;
+        let hit_mine = self.exploded.map(|tile| self.board.coords_of(tile));
+        (0..self.game_rows)
+            .step_by(block_size)
+            .map(|block_y| {
+                (0..self.game_cols)
+                    .step_by(block_size)
+                    .map(|block_x| {
+                        if let Some((x, y)) = hit_mine {
+                            if x / block_size == block_x / block_size
+                                && y / block_size == block_y / block_size
+                            {
+                                return 'X';
+                            }
+                        }
+                        let (mut safe, mut revealed) = (0, 0);
+                        for y in block_y..(block_y + block_size).min(self.game_rows) {
+                            for x in block_x..(block_x + block_size).min(self.game_cols) {
+                                let tile = self.board[self.board.index_of((x, y))];
+                                if tile.is_active() && !tile.is_a_mine() {
+                                    safe += 1;
+                                    revealed += tile.is_revealed() as usize;
+                                }
+                            }
+                        }
+                        match revealed {
+                            _ if safe == 0 || revealed == safe => '#',
+                            0 => '.',
+                            _ => '+',
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_are_counted_from_the_epoch() {
+        assert_eq!(civil_from_days(0), (1970, 1, 1));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+        assert_eq!(civil_from_days(59), (1970, 3, 1));
+    }
+
+    #[test]
+    fn leap_days_follow_the_gregorian_calendar() {
+        // 2000 is a leap year, since it is divisible by 400.
+        assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+        assert_eq!(civil_from_days(11_017), (2000, 3, 1));
+        assert_eq!(civil_from_days(19_416), (2023, 2, 28));
+        assert_eq!(civil_from_days(19_417), (2023, 3, 1));
+        // 2100 isn't, since it is divisible by 100.
+        assert_eq!(civil_from_days(47_540), (2100, 2, 28));
+        assert_eq!(civil_from_days(47_541), (2100, 3, 1));
+    }
+
+    #[test]
+    fn years_end_on_new_years_eve() {
+        assert_eq!(civil_from_days(18_627), (2020, 12, 31));
+        assert_eq!(civil_from_days(18_628), (2021, 1, 1));
+        assert_eq!(civil_from_days(10_956), (1999, 12, 31));
+        assert_eq!(civil_from_days(10_957), (2000, 1, 1));
+    }
+
+    #[test]
+    fn results_are_read_back() {
+        for &outcome in &[
+            DailyOutcome::Won,
+            DailyOutcome::HitAMine,
+            DailyOutcome::Forfeited,
+        ] {
+            let result = DailyResult {
+                date: "2021-03-01".to_string(),
+                difficulty: "Beginner".to_string(),
+                outcome,
+                time: Duration::from_millis(12_345),
+                three_bv: 17,
+                grid: vec!["#+".to_string(), ".X".to_string()],
+            };
+            assert_eq!(DailyResult::parse(&result.to_line()), Some(result));
+        }
+    }
+}
@@ -0,0 +1,482 @@
+use std::collections::HashMap;
+
+use ggez::{
+    event::{self, EventHandler, KeyCode, KeyMods},
+    graphics::{self, spritebatch::SpriteBatch, DrawMode, DrawParam, Rect, Text},
+    input::{keyboard, mouse},
+    nalgebra::{Point2, Vector2},
+    Context, GameResult,
+};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    board::{Mark, Tile},
+    clock::{format_duration, GameClock},
+    config::{Command, KeyBindings, MouseButtons},
+    event_handler::draw_paused,
+    game::{Playfield, Revealed},
+    seed::fnv1a,
+    theme::{Sprite, Theme},
+    topology::{Neighbourhood, Topology},
+    DEFAULT_TILE_SIZE,
+};
+
+/// The width and height of a chunk, in tiles.
+const CHUNK_SIZE: i64 = 16;
+/// How far the camera moves each frame while an arrow key is held, in pixels.
+const SCROLL_SPEED: f32 = 12.0;
+/// The height of the status bar at the bottom of the window, in pixels.
+pub(crate) const STATUS_BAR_HEIGHT: f32 = 15.0;
+
+/// An endless board is a square grid, where the 8 surrounding tiles are the neighbours.
+const NEIGHBOURHOOD: Neighbourhood = Neighbourhood::Adjacent;
+/// Every tile of an endless board holds one mine at most.
+const MAX_MINES_PER_TILE: u8 = 1;
+
+/// A block of `CHUNK_SIZE` x `CHUNK_SIZE` tiles, stored row by row.
+type Chunk = Vec<Tile>;
+
+/// Sparse storage of an endless board, made of chunks that are generated the first time they are accessed.
+///
+/// The mines of a chunk only depend on the seed and on the coordinates of the chunk,
+/// so the board comes out the same no matter in which order it is explored.
+pub(crate) struct ChunkedBoard {
+    chunks: HashMap<(i64, i64), Chunk>,
+    seed: u64,
+    /// The probability of each tile being a mine.
+    density: f64,
+}
+
+impl ChunkedBoard {
+    pub(crate) fn new(seed: &str, density: f64) -> ChunkedBoard {
+        ChunkedBoard {
+            chunks: HashMap::new(),
+            seed: fnv1a(seed.as_bytes()),
+            density,
+        }
+    }
+
+    /// The chunk the given coordinates are in, and the index of the tile in the chunk.
+    fn chunk_of((x, y): (i64, i64)) -> ((i64, i64), usize) {
+        let chunk = (x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE));
+        let index = (y.rem_euclid(CHUNK_SIZE) * CHUNK_SIZE + x.rem_euclid(CHUNK_SIZE)) as usize;
+        (chunk, index)
+    }
+
+    /// The amount of mines surrounding the given tile, generating the neighbouring chunks if needed.
+    fn mines_around(&mut self, coords: (i64, i64)) -> u8 {
+        self.neighbours(coords)
+            .into_iter()
+            .map(|neighbour| self.tile_mut(neighbour).mines())
+            .sum()
+    }
+
+    fn generate_chunk(seed: u64, density: f64, (chunk_x, chunk_y): (i64, i64)) -> Chunk {
+        let key = [
+            seed.to_le_bytes(),
+            chunk_x.to_le_bytes(),
+            chunk_y.to_le_bytes(),
+        ]
+        .concat();
+        let mut rng = ChaCha8Rng::seed_from_u64(fnv1a(&key));
+
+        (0..CHUNK_SIZE * CHUNK_SIZE)
+            .map(|index| {
+                let x = chunk_x * CHUNK_SIZE + index % CHUNK_SIZE;
+                let y = chunk_y * CHUNK_SIZE + index / CHUNK_SIZE;
+                let is_mine = rng.gen_bool(density);
+                // The starting tile and its neighbours are never mined, so that the game starts with an opening.
+                let is_start =
+                    (x, y) == (0, 0) || NEIGHBOURHOOD.offsets().contains(&(x as isize, y as isize));
+
+                let mut tile = Tile::default();
+                tile.set_mines(if is_mine && !is_start { 1 } else { 0 });
+                tile
+            })
+            .collect()
+    }
+}
+
+impl Playfield for ChunkedBoard {
+    type Pos = (i64, i64);
+
+    fn tile(&self, coords: (i64, i64)) -> Tile {
+        let (chunk, index) = ChunkedBoard::chunk_of(coords);
+        self.chunks
+            .get(&chunk)
+            .map_or_else(Tile::default, |chunk| chunk[index])
+    }
+
+    /// The tile at the given coordinates, generating its chunk if needed.
+    fn tile_mut(&mut self, coords: (i64, i64)) -> &mut Tile {
+        let (seed, density) = (self.seed, self.density);
+        let (chunk, index) = ChunkedBoard::chunk_of(coords);
+        &mut self
+            .chunks
+            .entry(chunk)
+            .or_insert_with(|| ChunkedBoard::generate_chunk(seed, density, chunk))[index]
+    }
+
+    fn neighbours(&self, (x, y): (i64, i64)) -> Vec<(i64, i64)> {
+        NEIGHBOURHOOD
+            .offsets()
+            .iter()
+            .map(|&(xd, yd)| (x + xd as i64, y + yd as i64))
+            .collect()
+    }
+
+    /// The numbers are only worked out as tiles are revealed, since they depend on the mines of the neighbouring
+    /// chunks. Iterative, since there is no telling up front how far an opening on an endless board reaches.
+    fn reveal_area(&mut self, start: (i64, i64)) -> usize {
+        let mut revealed = 0;
+        let mut stack = vec![start];
+        while let Some(coords) = stack.pop() {
+            let number = self.mines_around(coords);
+            let tile = self.tile_mut(coords);
+            if tile.is_revealed() || tile.is_flagged() || tile.is_a_mine() {
+                continue;
+            }
+            tile.set_number(Some(number));
+            tile.set_revealed(true);
+            revealed += 1;
+            if number == 0 {
+                stack.extend(self.neighbours(coords));
+            }
+        }
+        revealed
+    }
+}
+
+/// An endless game, played on a board that extends in every direction. The score is the amount of tiles
+/// cleared before hitting a mine.
+pub(crate) struct EndlessGame {
+    board: ChunkedBoard,
+    /// The screen position of the top-left corner of the tile at (0, 0), which moves when scrolling.
+    camera: (f32, f32),
+    theme: Theme,
+    sprite_batch: SpriteBatch,
+    tiles_revealed: usize,
+    tiles_flagged: isize,
+    clock: GameClock,
+    /// The mine that ended the game, `None` while the game goes on.
+    exploded: Option<(i64, i64)>,
+    pub(crate) keys: KeyBindings,
+    pub(crate) mouse: MouseButtons,
+    pub(crate) question_marks: bool,
+    /// Whether revealing a revealed number chords it.
+    pub(crate) reveal_chords: bool,
+    /// Whether flagging a revealed number flags its hidden neighbours, when they must all be mines.
+    pub(crate) flag_chords: bool,
+}
+
+/// The camera that puts the tile at (0, 0) in the middle of the screen.
+fn centred_camera(ctx: &Context) -> (f32, f32) {
+    let (width, height) = graphics::drawable_size(ctx);
+    (
+        ((width - DEFAULT_TILE_SIZE) / 2.0).floor(),
+        ((height - STATUS_BAR_HEIGHT - DEFAULT_TILE_SIZE) / 2.0).floor(),
+    )
+}
+
+impl EndlessGame {
+    /// Creates a new endless game drawn with the given theme, with the starting tile in the middle of the screen.
+    pub(crate) fn new(ctx: &mut Context, seed: &str, density: f64, theme: Theme) -> EndlessGame {
+        let img = theme
+            .spritesheet(ctx, &Topology::Square)
+            .expect("Image loading error");
+        EndlessGame {
+            board: ChunkedBoard::new(seed, density),
+            camera: centred_camera(ctx),
+            theme,
+            sprite_batch: SpriteBatch::new(img),
+            tiles_revealed: 0,
+            tiles_flagged: 0,
+            clock: GameClock::default(),
+            exploded: None,
+            keys: KeyBindings::default(),
+            mouse: MouseButtons::default(),
+            question_marks: false,
+            reveal_chords: false,
+            flag_chords: false,
+        }
+    }
+
+    /// Starts the game over on the same board, since the board only depends on the seed.
+    fn restart(&mut self, ctx: &Context) {
+        self.board.chunks.clear();
+        self.camera = centred_camera(ctx);
+        self.tiles_revealed = 0;
+        self.tiles_flagged = 0;
+        self.clock = GameClock::default();
+        self.exploded = None;
+    }
+
+    /// The coordinates of the tile at the given screen position.
+    fn tile_at(&self, (x, y): (f32, f32)) -> (i64, i64) {
+        (
+            ((x - self.camera.0) / DEFAULT_TILE_SIZE).floor() as i64,
+            ((y - self.camera.1) / DEFAULT_TILE_SIZE).floor() as i64,
+        )
+    }
+
+    /// Reveals the given tile. Chording stops at the first mine it hits, so that the score is the tiles cleared
+    /// before the game was lost.
+    fn reveal(&mut self, coords: (i64, i64)) {
+        if self.exploded.is_some() {
+            return;
+        }
+        match self.board.reveal(coords) {
+            Revealed::Nothing => {}
+            Revealed::Mine => {
+                self.clock.start();
+                self.exploded = Some(coords);
+                self.clock.stop();
+            }
+            Revealed::Tiles(revealed) => {
+                self.clock.start();
+                self.tiles_revealed += revealed;
+            }
+        }
+    }
+
+    fn toggle_flag(&mut self, coords: (i64, i64)) {
+        self.tiles_flagged +=
+            self.board
+                .cycle_mark(coords, MAX_MINES_PER_TILE, self.question_marks);
+    }
+
+    fn chord(&mut self, coords: (i64, i64)) {
+        for neighbour in self.board.chord_targets(coords) {
+            self.reveal(neighbour);
+        }
+    }
+
+    fn flag_chord(&mut self, coords: (i64, i64)) {
+        for neighbour in self.board.flag_chord_targets(coords, MAX_MINES_PER_TILE) {
+            self.toggle_flag(neighbour);
+        }
+    }
+
+    /// Carries out what a key or mouse button does, on the tile at the given position on the screen.
+    /// Hints and undoing are only for games on a board of a given size, and do nothing here.
+    fn run_command(&mut self, ctx: &mut Context, command: Command, position: (f32, f32)) {
+        let coords = self.tile_at(position);
+        let playing = !self.clock.is_paused() && self.exploded.is_none();
+        let revealed = self.board.tile(coords).is_revealed();
+        match command {
+            Command::Reveal if playing && revealed && self.reveal_chords => self.chord(coords),
+            Command::Reveal if playing => self.reveal(coords),
+            Command::Flag if playing && revealed && self.flag_chords => self.flag_chord(coords),
+            Command::Flag if playing => self.toggle_flag(coords),
+            Command::Chord if playing => self.chord(coords),
+            Command::Restart => self.restart(ctx),
+            Command::Pause if self.clock.is_paused() => self.clock.resume(),
+            Command::Pause => self.clock.pause(),
+            Command::Quit => event::quit(ctx),
+            _ => {}
+        }
+    }
+
+    /// The hidden tiles that are drawn pressed down while a mouse button that reveals or chords is held:
+    /// the tile under the mouse, along with its neighbours when chording.
+    fn pressed_tiles(&self, ctx: &Context) -> Vec<(i64, i64)> {
+        if self.clock.is_paused() || self.exploded.is_some() {
+            return Vec::new();
+        }
+        let position = mouse::position(ctx);
+        let coords = self.tile_at((position.x, position.y));
+        self.mouse.held_command(ctx).pressed_tiles(
+            coords,
+            self.reveal_chords && self.board.tile(coords).is_revealed(),
+            || self.board.neighbours(coords),
+            |coords| {
+                let tile = self.board.tile(coords);
+                !tile.is_revealed() && tile.mark() == Mark::None
+            },
+        )
+    }
+}
+
+impl EventHandler for EndlessGame {
+    fn mouse_button_down_event(
+        &mut self,
+        ctx: &mut Context,
+        button: event::MouseButton,
+        x: f32,
+        y: f32,
+    ) {
+        let command = self.mouse.pressed_command(ctx, button);
+        self.run_command(ctx, command, (x, y));
+    }
+
+    /// Scrolls the board, one tile per step of the mouse wheel.
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, x: f32, y: f32) {
+        self.camera.0 += x * DEFAULT_TILE_SIZE;
+        self.camera.1 += y * DEFAULT_TILE_SIZE;
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        _keymods: KeyMods,
+        repeat: bool,
+    ) {
+        if repeat {
+            return;
+        }
+        let position = mouse::position(ctx);
+        let command = self.keys.command(keycode);
+        self.run_command(ctx, command, (position.x, position.y));
+    }
+
+    /// Pauses the game when the window loses focus, so that the timer doesn't run while the player is away.
+    fn focus_event(&mut self, _ctx: &mut Context, gained: bool) {
+        if !gained {
+            self.clock.pause();
+        }
+    }
+
+    /// Moves the camera while the arrow keys are held.
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        while ggez::timer::check_update_time(ctx, 60) {
+            let held = |key| keyboard::is_key_pressed(ctx, key);
+            if held(KeyCode::Left) {
+                self.camera.0 += SCROLL_SPEED;
+            }
+            if held(KeyCode::Right) {
+                self.camera.0 -= SCROLL_SPEED;
+            }
+            if held(KeyCode::Up) {
+                self.camera.1 += SCROLL_SPEED;
+            }
+            if held(KeyCode::Down) {
+                self.camera.1 -= SCROLL_SPEED;
+            }
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        graphics::clear(ctx, self.theme.background);
+        let (width, height) = graphics::drawable_size(ctx);
+        let height = height - STATUS_BAR_HEIGHT;
+
+        // Only the tiles that are on the screen are drawn. Tiles of chunks that haven't been generated are hidden.
+        let (first_x, first_y) = self.tile_at((0.0, 0.0));
+        let (last_x, last_y) = self.tile_at((width, height));
+        let mut sprite_batch = self.sprite_batch.clone();
+        let pressed = self.pressed_tiles(ctx);
+        let lost = self.exploded.is_some();
+        for y in first_y..=last_y {
+            for x in first_x..=last_x {
+                // Once the game is lost, the mines of the chunks that have been generated are shown.
+                let sprite = if self.clock.is_paused() {
+                    Sprite::Hidden
+                } else if self.exploded == Some((x, y)) {
+                    Sprite::ExplodedMine
+                } else if pressed.contains(&(x, y)) {
+                    Sprite::Pressed
+                } else {
+                    Sprite::of(self.board.tile((x, y)), lost)
+                };
+                let scale = DEFAULT_TILE_SIZE / self.theme.tile_size;
+                sprite_batch.add(
+                    DrawParam::new()
+                        .src(self.theme.source(&Topology::Square, sprite))
+                        .scale(Vector2::new(scale, scale))
+                        .dest(Point2::new(
+                            self.camera.0 + x as f32 * DEFAULT_TILE_SIZE,
+                            self.camera.1 + y as f32 * DEFAULT_TILE_SIZE,
+                        )),
+                );
+            }
+        }
+        graphics::draw(ctx, &sprite_batch, (Point2::<f32>::new(0.0, 0.0),))?;
+
+        let status_bar = graphics::Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(0.0, height, width, STATUS_BAR_HEIGHT),
+            self.theme.background,
+        )?;
+        graphics::draw(ctx, &status_bar, (Point2::<f32>::new(0.0, 0.0),))?;
+
+        let status = if self.exploded.is_some() {
+            format!(
+                "Game over! You cleared {} tiles in {}",
+                self.tiles_revealed,
+                format_duration(self.clock.elapsed())
+            )
+        } else {
+            format!(
+                "Tiles cleared: {}    Flags: {}    Elapsed time: {}",
+                self.tiles_revealed,
+                self.tiles_flagged,
+                format_duration(self.clock.elapsed())
+            )
+        };
+        let mut status = Text::new(status);
+        status.set_font(
+            graphics::Font::default(),
+            graphics::Scale { x: 15.0, y: 15.0 },
+        );
+        graphics::draw(
+            ctx,
+            &status,
+            (Point2::<f32>::new(0.0, height), self.theme.text),
+        )?;
+
+        if self.clock.is_paused() {
+            draw_paused(ctx, &self.theme, &self.keys, (width, height))?;
+        }
+
+        graphics::present(ctx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_board_does_not_depend_on_the_order_it_is_explored() {
+        let coords: Vec<(i64, i64)> = (-40..40)
+            .flat_map(|y| (-40..40).map(move |x| (x, y)))
+            .collect();
+        let mut forwards = ChunkedBoard::new("seed", 0.2);
+        let mut backwards = ChunkedBoard::new("seed", 0.2);
+        for &tile in coords.iter().rev() {
+            backwards.tile_mut(tile);
+        }
+        for &tile in &coords {
+            assert_eq!(
+                forwards.tile_mut(tile).mines(),
+                backwards.tile(tile).mines()
+            );
+        }
+    }
+
+    #[test]
+    fn the_game_starts_with_an_opening() {
+        let mut board = ChunkedBoard::new("seed", 0.5);
+        assert!(matches!(board.reveal((0, 0)), Revealed::Tiles(revealed) if revealed >= 9));
+        assert!(board
+            .neighbours((0, 0))
+            .into_iter()
+            .all(|tile| board.tile(tile).is_revealed()));
+    }
+
+    #[test]
+    fn marks_cycle_through_question_marks() {
+        let mut board = ChunkedBoard::new("seed", 0.2);
+        let tile = (100, -100);
+        assert_eq!(board.cycle_mark(tile, MAX_MINES_PER_TILE, true), 1);
+        assert_eq!(board.tile(tile).mark(), Mark::Flag);
+        assert_eq!(board.cycle_mark(tile, MAX_MINES_PER_TILE, true), -1);
+        assert_eq!(board.tile(tile).mark(), Mark::Question);
+        assert_eq!(board.cycle_mark(tile, MAX_MINES_PER_TILE, true), 0);
+        assert_eq!(board.tile(tile).mark(), Mark::None);
+    }
+}
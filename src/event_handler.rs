@@ -1,16 +1,160 @@
-use std::time::Duration;
-
 use ggez::{event, graphics::Text, nalgebra::Vector2};
 use ggez::{
-    event::EventHandler,
-    graphics::{self, DrawParam, Rect},
+    event::{EventHandler, KeyCode, KeyMods},
+    graphics::{self, DrawMode, DrawParam, Rect},
     input::mouse,
     nalgebra::Point2,
 };
 
-use crate::{game::GameContainer, DEFAULT_TILE_SIZE, SPRITESHEET_WIDTH};
+use crate::{
+    board::{Mark, MineDistributionError},
+    clock::format_duration,
+    config::{Command, KeyBindings},
+    coop::player_colour,
+    game::{Action, GameContainer, GameMode, GameState},
+    theme::{Sprite, Theme},
+    topology::Topology,
+};
+
+/// Tells that the game is paused, and which key resumes it, in the middle of an area of the given size.
+pub(crate) fn draw_paused(
+    ctx: &mut ggez::Context,
+    theme: &Theme,
+    keys: &KeyBindings,
+    area: (f32, f32),
+) -> ggez::GameResult {
+    let paused = match keys.pause.as_str() {
+        "" => "Paused".to_string(),
+        key => format!("Paused, press {} to resume", key),
+    };
+    draw_notice(ctx, theme, paused, area)
+}
 
-const TILE_IMAGE_FRACTION: f32 = DEFAULT_TILE_SIZE / SPRITESHEET_WIDTH;
+/// Draws a line of text in the middle of an area of the given size.
+fn draw_notice(
+    ctx: &mut ggez::Context,
+    theme: &Theme,
+    notice: String,
+    (width, height): (f32, f32),
+) -> ggez::GameResult {
+    let mut notice = Text::new(notice);
+    notice.set_font(
+        graphics::Font::default(),
+        graphics::Scale { x: 20.0, y: 20.0 },
+    );
+    let (text_width, text_height) = notice.dimensions(ctx);
+    graphics::draw(
+        ctx,
+        &notice,
+        (
+            Point2::<f32>::new(
+                (width - text_width as f32) / 2.0,
+                (height - text_height as f32) / 2.0,
+            ),
+            theme.text,
+        ),
+    )
+}
+
+impl GameContainer {
+    /// Carries out what a key or mouse button does. Revealing, flagging and chording act on the tile at the given
+    /// position on the screen, which is where the mouse is.
+    fn run_command(&mut self, ctx: &mut ggez::Context, command: Command, position: (f32, f32)) {
+        let result = match command {
+            Command::Reveal | Command::Flag | Command::Chord => self.act_at(command, position),
+            Command::Restart => {
+                self.restart();
+                Ok(())
+            }
+            Command::Pause => {
+                self.toggle_pause();
+                Ok(())
+            }
+            Command::Hint => self.hint(),
+            Command::Undo => {
+                self.undo();
+                Ok(())
+            }
+            Command::Quit => {
+                self.forfeit_daily();
+                event::quit(ctx);
+                Ok(())
+            }
+            Command::Nothing => Ok(()),
+        };
+        if let Err(e) = result {
+            println!("{}", e);
+            event::quit(ctx);
+        }
+    }
+
+    /// Reveals, flags or chords the tile at the given position on the screen.
+    /// Revealing and flagging a revealed number may chord it instead, if the player has chosen so.
+    fn act_at(&mut self, command: Command, position: (f32, f32)) -> Result<(), MineDistributionError> {
+        // Spectators only watch.
+        if self.is_paused() || self.state != GameState::Playing || matches!(self.mode, GameMode::Spectate(_)) {
+            return Ok(());
+        }
+        let tile_index = match self
+            .topology
+            .tile_at(&self.board, position, self.scaled_tile_size)
+        {
+            Some(tile_index) if self.board[tile_index].is_active() => tile_index,
+            _ => return Ok(()),
+        };
+        let revealed = self.board[tile_index].is_revealed();
+        let actions = match command {
+            Command::Reveal if revealed && self.reveal_chords => vec![Action::Chord(tile_index)],
+            Command::Reveal => vec![Action::Reveal(tile_index)],
+            Command::Flag if revealed && self.flag_chords => self.flag_chord_actions(tile_index),
+            Command::Flag => vec![Action::Flag(tile_index)],
+            _ => vec![Action::Chord(tile_index)],
+        };
+        for action in actions {
+            // Players of a versus game can only reveal tiles.
+            if self.versus().is_some() && action != Action::Reveal(tile_index) {
+                continue;
+            }
+            self.act(action)?;
+        }
+        Ok(())
+    }
+
+    /// The hidden tiles that are drawn pressed down while a mouse button that reveals or chords is held:
+    /// the tile under the mouse, along with its neighbours when chording.
+    fn pressed_tiles(&self, ctx: &ggez::Context) -> Vec<usize> {
+        if self.is_paused() || self.state != GameState::Playing || matches!(self.mode, GameMode::Spectate(_)) {
+            return Vec::new();
+        }
+        // Players of a versus game can only reveal tiles.
+        let versus = self.versus().is_some();
+        let command = match self.mouse.held_command(ctx) {
+            Command::Chord if versus => return Vec::new(),
+            command => command,
+        };
+        let position = mouse::position(ctx);
+        let tile_index = match self
+            .topology
+            .tile_at(&self.board, (position.x, position.y), self.scaled_tile_size)
+        {
+            Some(tile_index) if self.board[tile_index].is_active() => tile_index,
+            _ => return Vec::new(),
+        };
+        let reveal_chords = self.reveal_chords && !versus && self.board[tile_index].is_revealed();
+        command.pressed_tiles(
+            tile_index,
+            reveal_chords,
+            || {
+                self.topology
+                    .neighbours(&self.board, tile_index, self.neighbourhood)
+            },
+            |tile_index| {
+                let tile = self.board[tile_index];
+                tile.is_active() && !tile.is_revealed() && tile.mark() == Mark::None
+            },
+        )
+    }
+}
 
 impl EventHandler for GameContainer {
     fn mouse_button_down_event(
@@ -20,86 +164,162 @@ impl EventHandler for GameContainer {
         x: f32,
         y: f32,
     ) {
-        let tile_x = (x / self.scaled_tile_size).floor() as usize;
-        let tile_y = (y / self.scaled_tile_size).floor() as usize;
-        match button {
-            event::MouseButton::Left => {
-                if self.tiles_revealed == 0{
-                    self.distribute_mines(Some((tile_x, tile_y)))
-                }
+        let command = self.mouse.pressed_command(ctx, button);
+        self.run_command(ctx, command, (x, y));
+    }
 
-                if mouse::button_pressed(ctx, event::MouseButton::Right) {
-                    // Chord if both the left and the right mouse buttons are pressed.
-                    self.chord_at((tile_x, tile_y))
-                } else {
-                    // Reveal
-                    self.reveal_tile_at((tile_x, tile_y));
-                }
-            }
-            ggez::event::MouseButton::Right => {
-                if mouse::button_pressed(ctx, event::MouseButton::Left) {
-                    // Chord if both the left and the right mouse buttons are pressed.
-                    self.chord_at((tile_x, tile_y))
-                } else {
-                    // Flag
-                    self.toggle_flag_at((tile_x, tile_y));
-                }
-            }
-            ggez::event::MouseButton::Middle => {
-                // Chord
-                self.chord_at((tile_x, tile_y));
-            }
-            _ => {}
+    /// Keys that are held down don't repeat their command.
+    fn key_down_event(
+        &mut self,
+        ctx: &mut ggez::Context,
+        keycode: KeyCode,
+        _keymods: KeyMods,
+        repeat: bool,
+    ) {
+        if repeat {
+            return;
+        }
+        let position = mouse::position(ctx);
+        let command = self.keys.command(keycode);
+        self.run_command(ctx, command, (position.x, position.y));
+    }
+
+    fn mouse_motion_event(&mut self, _ctx: &mut ggez::Context, x: f32, y: f32, _dx: f32, _dy: f32) {
+        self.move_cursor((x, y));
+    }
+
+    /// Closing the window in the middle of a daily challenge forfeits it.
+    fn quit_event(&mut self, _ctx: &mut ggez::Context) -> bool {
+        self.forfeit_daily();
+        false
+    }
+
+    /// Pauses the game when the window loses focus, so that the timer doesn't run while the player is away.
+    fn focus_event(&mut self, _ctx: &mut ggez::Context, gained: bool) {
+        if !gained && !self.is_paused() {
+            self.toggle_pause();
         }
     }
 
     fn update(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
         while ggez::timer::check_update_time(ctx, 60) {}
+        self.poll_race();
+        if let Err(e) = self
+            .poll_coop()
+            .and_then(|_| self.poll_versus())
+            .and_then(|_| self.poll_spectators())
+        {
+            println!("{}", e);
+            event::quit(ctx);
+        }
+        self.save_daily_result();
         Ok(())
     }
 
     // TODO: Rita endast upp de som det har blivit ändring på.
     fn draw(&mut self, ctx: &mut ggez::Context) -> ggez::GameResult {
-        graphics::clear(ctx, graphics::Color::from_rgb(50, 50, 50));
+        graphics::clear(ctx, self.theme.background);
         let mut sprite_batch = self.sprite_batch.clone();
-        let tile_array = &*self.tile_array;
-        for (x, vec) in tile_array.into_iter().enumerate() {
-            for (y, tile) in vec.into_iter().enumerate() {
-                // Sets the "source" of the image for each tile, which is a part of the /resources/spritesheet.bmp image which is loaded into the sprite batch.
-                let src = if tile.is_flagged {
-                    Rect::new(2.0 * TILE_IMAGE_FRACTION, 0.0, TILE_IMAGE_FRACTION, 1.0)
-                } else {
-                    match (tile.is_revealed, tile.number) {
-                        (false, _) => Rect::new(TILE_IMAGE_FRACTION, 0.0, TILE_IMAGE_FRACTION, 1.0),
-
-                        (true, Some(number)) => Rect::new(
-                            TILE_IMAGE_FRACTION * (2.0 + number as f32),
-                            0.0,
-                            TILE_IMAGE_FRACTION,
-                            1.0,
-                        ),
-                        (true, None) => Rect::new(0.0, 0.0, TILE_IMAGE_FRACTION, 1.0),
+        let mut large_numbers = Vec::new();
+        let pressed = self.pressed_tiles(ctx);
+        let lost = self.state == GameState::Lost;
+        for (tile_index, tile) in self.board.iter() {
+            // Disabled tiles are holes in the board, which show the background.
+            if !tile.is_active() {
+                continue;
+            }
+            let (x, y) = self
+                .topology
+                .tile_position(&self.board, tile_index, self.scaled_tile_size);
+            let sprite_rows = self.topology.sprite_rows() as f32;
+            let sprite_row = self.topology.sprite_row(&self.board, tile_index) as f32;
+            // Sets the "source" of the image for each tile, which is a part of the theme's spritesheet which is loaded into the sprite batch.
+            // All tiles are drawn as hidden while the game is paused, so that the board can't be studied.
+            // Mines claimed in a versus game are shown as flags in the colour of the player who claimed them.
+            // A lost game shows where the mines were, and which flags were wrong.
+            let claim = self
+                .versus()
+                .and_then(|versus| versus.claims.get(&tile_index));
+            let sprite = if self.is_paused() {
+                Sprite::Hidden
+            } else if claim.is_some() {
+                Sprite::Flags(tile.mines())
+            } else if lost && self.exploded == Some(tile_index) {
+                Sprite::ExplodedMine
+            } else if pressed.contains(&tile_index) {
+                Sprite::Pressed
+            } else {
+                match Sprite::of(tile, lost) {
+                    Sprite::Number(number) if !self.theme.has_number_sprite(number) => {
+                        large_numbers.push((tile_index, number));
+                        Sprite::Empty
                     }
-                };
+                    sprite => sprite,
+                }
+            };
 
-                sprite_batch.add(
-                    DrawParam::new()
-                        .src(src)
-                        .scale(Vector2::new(
-                            self.scaled_tile_size / DEFAULT_TILE_SIZE,
-                            self.scaled_tile_size / DEFAULT_TILE_SIZE,
-                        ))
-                        .dest(Point2::new(
-                            x as f32 * self.scaled_tile_size,
-                            y as f32 * self.scaled_tile_size,
-                        )),
-                );
-            }
+            let src = self.theme.source(&self.topology, sprite);
+            let src = Rect::new(src.x, sprite_row / sprite_rows, src.w, src.h / sprite_rows);
+
+            sprite_batch.add(
+                DrawParam::new()
+                    .src(src)
+                    .scale(Vector2::new(
+                        self.scaled_tile_size / self.theme.tile_size,
+                        self.scaled_tile_size / self.theme.tile_size,
+                    ))
+                    .dest(Point2::new(x, y))
+                    .color(claim.map_or(graphics::WHITE, |&player| player_colour(player))),
+            );
         }
 
         graphics::draw(ctx, &sprite_batch, (Point2::<f32>::new(0.0, 0.0),))
             .expect("Something went wrong rendering the game.");
 
+        for (tile_index, number) in large_numbers {
+            let (x, y) = self
+                .topology
+                .tile_position(&self.board, tile_index, self.scaled_tile_size);
+            let mut text = Text::new(number.to_string());
+            text.set_font(
+                graphics::Font::default(),
+                graphics::Scale {
+                    x: self.scaled_tile_size / 2.0,
+                    y: self.scaled_tile_size / 2.0,
+                },
+            );
+            let (width, height) = text.dimensions(ctx);
+            graphics::draw(
+                ctx,
+                &text,
+                (
+                    Point2::<f32>::new(
+                        x + (self.scaled_tile_size - width as f32) / 2.0,
+                        y + (self.scaled_tile_size - height as f32) / 2.0,
+                    ),
+                    self.theme.text,
+                ),
+            )?;
+        }
+
+        let (board_width, board_height) = self.board_size();
+
+        // Outlines the board on a wrap-around board, as a cue that its edges continue on the opposite side.
+        if self.topology == Topology::Torus {
+            let outline = graphics::Mesh::new_rectangle(
+                ctx,
+                DrawMode::stroke(3.0),
+                Rect::new(
+                    1.5,
+                    1.5,
+                    board_width - 3.0,
+                    board_height - 3.0,
+                ),
+                graphics::Color::from_rgb(0, 160, 170),
+            )?;
+            graphics::draw(ctx, &outline, (Point2::<f32>::new(0.0, 0.0),))?;
+        }
+
 
         // let a = ggez::graphics::Mesh::new_circle(
         //     ctx,
@@ -112,20 +332,143 @@ impl EventHandler for GameContainer {
         // .unwrap();
 
 
-            let current_time = self.start_time.unwrap_or(std::time::SystemTime::now()).elapsed().unwrap_or(std::time::Duration::from_secs(0));
-            let mut elapsed_time = Text::new(format!("Elapsed time: {}s", current_time.as_secs().to_string()));
+            let status = match self.state {
+                GameState::Playing => "Elapsed time",
+                _ if self.versus().is_some() => "Finished in",
+                GameState::Won => "You won in",
+                GameState::Lost => "You lost after",
+            };
+            let mut elapsed_time = Text::new(format!("{}: {}", status, format_duration(self.elapsed())));
             elapsed_time.set_font(
                 graphics::Font::default(),
                 graphics::Scale { x: 15.0, y: 15.0 },
             );
-            graphics::draw(ctx, &elapsed_time, (Point2::<f32>::new(0.0, self.game_rows as f32*self.scaled_tile_size),))?;
+            graphics::draw(ctx, &elapsed_time, (Point2::<f32>::new(0.0, board_height), self.theme.text))?;
 
         let mut remaining_mines = Text::new(format!("Remaining mines: {}", self.game_mines as isize -self.tiles_flagged));
         remaining_mines.set_font(
             graphics::Font::default(),
             graphics::Scale { x: 15.0, y: 15.0 },
         );
-        graphics::draw(ctx, &remaining_mines, (Point2::<f32>::new(150.0, self.game_rows as f32*self.scaled_tile_size),))?;
+        graphics::draw(ctx, &remaining_mines, (Point2::<f32>::new(180.0, board_height), self.theme.text))?;
+
+        // The cursors of the other players of a co-op game, in their colours and with their names.
+        if let GameMode::Coop(coop) = &self.mode {
+            for (player, cursor) in coop.cursors.iter().enumerate() {
+                let (x, y) = match cursor {
+                    Some((x, y)) if player != coop.player => {
+                        (x * self.scaled_tile_size, y * self.scaled_tile_size)
+                    }
+                    _ => continue,
+                };
+                let colour = player_colour(player);
+                let pointer = graphics::Mesh::new_circle(
+                    ctx,
+                    DrawMode::fill(),
+                    Point2::new(x, y),
+                    5.0,
+                    0.5,
+                    colour,
+                )?;
+                graphics::draw(ctx, &pointer, (Point2::<f32>::new(0.0, 0.0),))?;
+                if let Some(name) = coop.names.get(player) {
+                    let mut text = Text::new(name.as_str());
+                    text.set_font(
+                        graphics::Font::default(),
+                        graphics::Scale { x: 13.0, y: 13.0 },
+                    );
+                    graphics::draw(
+                        ctx,
+                        &text,
+                        DrawParam::new()
+                            .dest(Point2::new(x + 7.0, y + 2.0))
+                            .color(colour),
+                    )?;
+                }
+            }
+        }
+
+        // The sidebar of a network game. A race ranks the players and shows how far they have come,
+        // a co-op game lists the players in the colours of their cursors, a versus game shows the score,
+        // and a spectator counts the player's clicks.
+        let mut sidebar = Vec::new();
+        if let GameMode::Race(race) = &self.mode {
+            sidebar.push(("Race".to_string(), self.theme.text));
+            for (rank, player) in race.ranking().into_iter().enumerate() {
+                let progress = match (player.state, player.time) {
+                    (GameState::Won, Some(time)) => format_duration(time),
+                    (GameState::Lost, _) => format!("hit a mine at {}%", player.percent),
+                    _ => format!("{}%", player.percent),
+                };
+                sidebar.push((
+                    format!("{}. {}: {}", rank + 1, player.name, progress),
+                    self.theme.text,
+                ));
+            }
+        }
+        if let GameMode::Coop(coop) = &self.mode {
+            sidebar.push(("Co-op".to_string(), self.theme.text));
+            for (player, name) in coop.names.iter().enumerate() {
+                sidebar.push((name.clone(), player_colour(player)));
+            }
+        }
+        if let GameMode::Versus(versus) = &self.mode {
+            sidebar.push(("Versus".to_string(), self.theme.text));
+            for (player, name) in versus.names.iter().enumerate() {
+                let turn = if self.state == GameState::Playing && versus.turn == player {
+                    "> "
+                } else {
+                    ""
+                };
+                sidebar.push((
+                    format!("{}{}: {} mines", turn, name, versus.scores[player]),
+                    player_colour(player),
+                ));
+            }
+            if self.state != GameState::Playing {
+                let result = match versus.leader() {
+                    Some(player) => format!("{} wins!", versus.names[player]),
+                    None => "It's a draw!".to_string(),
+                };
+                sidebar.push((result, self.theme.text));
+            }
+        }
+        if matches!(self.mode, GameMode::Spectate(_)) {
+            let (reveals, flags, chords) = self.click_counts();
+            sidebar.push(("Spectating".to_string(), self.theme.text));
+            sidebar.push((format!("Clicks: {}", reveals + flags + chords), self.theme.text));
+            sidebar.push((format!("Reveals: {}", reveals), self.theme.text));
+            sidebar.push((format!("Flags: {}", flags), self.theme.text));
+            sidebar.push((format!("Chords: {}", chords), self.theme.text));
+        }
+        for (line_number, (line, colour)) in sidebar.into_iter().enumerate() {
+            let mut text = Text::new(line);
+            text.set_font(
+                graphics::Font::default(),
+                graphics::Scale { x: 15.0, y: 15.0 },
+            );
+            graphics::draw(
+                ctx,
+                &text,
+                DrawParam::new()
+                    .dest(Point2::new(
+                        board_width + 10.0,
+                        10.0 + line_number as f32 * 20.0,
+                    ))
+                    .color(colour),
+            )?;
+        }
+
+        if self.is_paused() {
+            draw_paused(ctx, &self.theme, &self.keys, (board_width, board_height))?;
+        } else if self.no_deduction {
+            draw_notice(
+                ctx,
+                &self.theme,
+                "No deduction available".to_string(),
+                (board_width, board_height),
+            )?;
+        }
 
         graphics::present(ctx)
     }
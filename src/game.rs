@@ -1,263 +1,818 @@
-use core::num;
-use std::{collections::hash_map::DefaultHasher, time::{Instant, SystemTime}};
-use std::hash::{Hash, Hasher};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    time::Duration,
+};
 
 use ggez::{
-    graphics::{spritebatch::SpriteBatch, Image},
+    graphics::spritebatch::SpriteBatch,
     Context,
 };
-use rand::random;
-
-/// 2-Dimensional vector of tiles.
-/// The outer vector is a vector of the game columns.
-/// Tiles at coordinates (x, y) can be accessed via `TileArray[x][y]`
-type TileArray = Vec<Vec<Tile>>;
-
-/// Game tile
-#[derive(Copy, Clone, Debug)]
-pub(crate) struct Tile {
-    pub(crate) is_revealed: bool,
-    is_a_mine: bool,
-    pub(crate) is_flagged: bool,
-    pub(crate) number: Option<u8>,
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::{
+    board::{Board, Mark, MineDistributionError, Tile},
+    clock::GameClock,
+    assist::Snapshot,
+    config::{KeyBindings, MouseButtons},
+    coop::Coop,
+    daily::Daily,
+    mask::Mask,
+    net::Message,
+    race::Race,
+    seed::seeded_rng,
+    spectate::{Spectating, Spectators},
+    theme::Theme,
+    topology::{Neighbourhood, Topology},
+    versus::Versus,
+};
+
+/// The most tiles a board can have across or down. Larger boards would be drawn with tiles of barely a pixel.
+pub(crate) const MAX_BOARD_SIDE: usize = 500;
+
+/// Decides which tiles are kept free from mines on the first click.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum FirstClickPolicy {
+    /// The first click may hit a mine.
+    Unprotected,
+    /// The clicked tile is never a mine.
+    SafeTile,
+    /// The clicked tile and its neighbours are never mines, so the first click always opens an area.
+    Opening,
+    /// The tiles in the 3x3 area (measured in tiles) centered on the clicked tile are never mines.
+    SafeArea,
+}
+impl fmt::Display for FirstClickPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FirstClickPolicy::Unprotected => "No protection",
+            FirstClickPolicy::SafeTile => "Safe tile",
+            FirstClickPolicy::Opening => "Guaranteed opening",
+            FirstClickPolicy::SafeArea => "Safe 3x3 area",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The tiles a first-click policy keeps free from mines, for a first click on any tile of a board.
+struct ProtectedArea<'a> {
+    board: &'a Board,
+    topology: &'a Topology,
+    neighbourhood: Neighbourhood,
+    policy: FirstClickPolicy,
+    /// The tiles by the whole-tile cell their position is in, so that the tiles of a 3x3 area are found without
+    /// looking through the whole board. Only filled in for `FirstClickPolicy::SafeArea`.
+    cells: HashMap<(i64, i64), Vec<usize>>,
+}
+
+impl<'a> ProtectedArea<'a> {
+    fn new(
+        board: &'a Board,
+        topology: &'a Topology,
+        neighbourhood: Neighbourhood,
+        policy: FirstClickPolicy,
+    ) -> ProtectedArea<'a> {
+        let policy = match (policy, topology) {
+            // The neighbours of a tile on a cube are exactly the 3x3x3 area around it.
+            (FirstClickPolicy::SafeArea, Topology::Cube { .. }) => FirstClickPolicy::Opening,
+            (policy, _) => policy,
+        };
+        let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        if policy == FirstClickPolicy::SafeArea {
+            for tile in 0..board.len() {
+                // Positions in tiles, so that the 3x3 area can be told apart on boards of any topology.
+                let (x, y) = topology.tile_position(board, tile, 1.0);
+                cells
+                    .entry((x.floor() as i64, y.floor() as i64))
+                    .or_default()
+                    .push(tile);
+            }
+        }
+        ProtectedArea {
+            board,
+            topology,
+            neighbourhood,
+            policy,
+            cells,
+        }
+    }
+
+    /// The tiles kept free from mines when the first click is on the given tile. May include disabled tiles.
+    fn tiles(&self, clicked_tile: usize) -> Vec<usize> {
+        let mut tiles = match self.policy {
+            FirstClickPolicy::Unprotected => Vec::new(),
+            FirstClickPolicy::SafeTile => vec![clicked_tile],
+            FirstClickPolicy::Opening => {
+                let mut tiles = self
+                    .topology
+                    .neighbours(self.board, clicked_tile, self.neighbourhood);
+                tiles.push(clicked_tile);
+                tiles
+            }
+            // The area wraps around the edges of a torus, where it is the tile and the 8 tiles around it.
+            FirstClickPolicy::SafeArea if matches!(self.topology, Topology::Torus) => {
+                let mut tiles = self
+                    .topology
+                    .neighbours(self.board, clicked_tile, Neighbourhood::Adjacent);
+                tiles.push(clicked_tile);
+                tiles
+            }
+            FirstClickPolicy::SafeArea => {
+                let position_of = |tile| self.topology.tile_position(self.board, tile, 1.0);
+                let (clicked_x, clicked_y) = position_of(clicked_tile);
+                let (cell_x, cell_y) = (clicked_x.floor() as i64, clicked_y.floor() as i64);
+                let mut tiles = Vec::new();
+                for y in cell_y - 2..=cell_y + 2 {
+                    for x in cell_x - 2..=cell_x + 2 {
+                        let cell = self.cells.get(&(x, y)).into_iter().flatten();
+                        tiles.extend(cell.copied().filter(|&tile| {
+                            let (x, y) = position_of(tile);
+                            (x - clicked_x).abs() < 1.5 && (y - clicked_y).abs() < 1.5
+                        }));
+                    }
+                }
+                tiles
+            }
+        };
+        tiles.sort_unstable();
+        tiles.dedup();
+        tiles
+    }
+}
+
+/// Statistics about a game, kept to tell whether results of different games are comparable.
+#[derive(Clone, Debug)]
+pub(crate) struct GameStats {
+    pub(crate) first_click_policy: FirstClickPolicy,
+    /// The time it took to win or lose the game, `None` while the game is in progress.
+    pub(crate) final_time: Option<Duration>,
+}
+
+/// An action a player can take on a tile.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Action {
+    Reveal(usize),
+    Flag(usize),
+    Chord(usize),
+}
+
+impl Action {
+    /// The tile the action is taken on.
+    pub(crate) fn tile(self) -> usize {
+        match self {
+            Action::Reveal(tile) | Action::Flag(tile) | Action::Chord(tile) => tile,
+        }
+    }
+}
+
+/// What revealing a tile came to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Revealed {
+    /// The tile is revealed or flagged already, so nothing happened.
+    Nothing,
+    Mine,
+    /// The tile was safe. Holds the amount of tiles that were revealed, including the ones around an empty tile.
+    Tiles(usize),
+}
+
+/// The tiles a game is played on, the way its actions see them. Games on a board of a given size and endless games
+/// both go through here, so that tiles are revealed, marked and chorded the same way in both.
+pub(crate) trait Playfield {
+    /// Where a tile is: its index on a board of a given size, or its coordinates on an endless board.
+    type Pos: Copy + PartialEq;
+
+    /// The tile at the given position. Tiles that haven't been generated yet are hidden,
+    /// which they always are since they are never next to a revealed tile.
+    fn tile(&self, pos: Self::Pos) -> Tile;
+
+    /// The tile at the given position, generating it if needed.
+    fn tile_mut(&mut self, pos: Self::Pos) -> &mut Tile;
+
+    /// The tiles neighbouring the given tile. Disabled tiles are never neighbours.
+    fn neighbours(&self, pos: Self::Pos) -> Vec<Self::Pos>;
+
+    /// Reveals the given tile, and keeps revealing the neighbours of every revealed tile without a number.
+    /// Returns the amount of tiles that were revealed.
+    fn reveal_area(&mut self, pos: Self::Pos) -> usize;
+
+    /// Reveals the given tile, unless it is revealed or flagged.
+    fn reveal(&mut self, pos: Self::Pos) -> Revealed {
+        let tile = *self.tile_mut(pos);
+        if tile.is_revealed() || tile.is_flagged() {
+            Revealed::Nothing
+        } else if tile.is_a_mine() {
+            Revealed::Mine
+        } else {
+            Revealed::Tiles(self.reveal_area(pos))
+        }
+    }
+
+    /// Cycles the mark of a hidden tile from no mark to flag, adding flags up to `max_flags`,
+    /// then to question mark if they are enabled, and back to no mark. Returns the change in the amount of flags.
+    fn cycle_mark(&mut self, pos: Self::Pos, max_flags: u8, question_marks: bool) -> isize {
+        let tile = self.tile_mut(pos);
+        if tile.is_revealed() {
+            return 0;
+        }
+        match tile.mark() {
+            Mark::None => {
+                tile.set_mark(Mark::Flag);
+                1
+            }
+            Mark::Flag if tile.flags() < max_flags => {
+                tile.set_flags(tile.flags() + 1);
+                1
+            }
+            Mark::Flag => {
+                let flags = tile.flags() as isize;
+                tile.set_mark(if question_marks {
+                    Mark::Question
+                } else {
+                    Mark::None
+                });
+                -flags
+            }
+            Mark::Question => {
+                tile.set_mark(Mark::None);
+                0
+            }
+        }
+    }
+
+    /// The tiles that chording a revealed number reveals: its neighbours, if it is surrounded by as many flags
+    /// as its number, and none otherwise. Stacked flags all count, question marks don't.
+    /// In a liar game, the shown number is used.
+    fn chord_targets(&self, pos: Self::Pos) -> Vec<Self::Pos> {
+        let tile = self.tile(pos);
+        let neighbours = self.neighbours(pos);
+        let flags: usize = neighbours
+            .iter()
+            .map(|&neighbour| self.tile(neighbour).flags() as usize)
+            .sum();
+        match tile.displayed_number() {
+            Some(number) if tile.is_revealed() && number as usize == flags => neighbours,
+            _ => Vec::new(),
+        }
+    }
+
+    /// The flags that mark every hidden neighbour of a revealed number as a mine, as the tile of each flag,
+    /// if the number leaves no doubt, i.e. if it equals the most mines the hidden neighbours can hold.
+    /// Empty if it doesn't. Flagging cycles through the marks, so a tile may need several flags.
+    /// In a liar game, the shown number is used.
+    fn flag_chord_targets(&self, pos: Self::Pos, max_flags: u8) -> Vec<Self::Pos> {
+        let tile = self.tile(pos);
+        let number = match tile.displayed_number() {
+            Some(number) if tile.is_revealed() => number as usize,
+            _ => return Vec::new(),
+        };
+        let hidden: Vec<Self::Pos> = self
+            .neighbours(pos)
+            .into_iter()
+            .filter(|&neighbour| !self.tile(neighbour).is_revealed())
+            .collect();
+        let max_flags = max_flags as usize;
+        if hidden.len() * max_flags != number {
+            return Vec::new();
+        }
+        hidden
+            .into_iter()
+            .flat_map(|neighbour| {
+                let neighbour_tile = self.tile(neighbour);
+                let flags = match neighbour_tile.mark() {
+                    Mark::None => max_flags,
+                    Mark::Flag => max_flags - neighbour_tile.flags() as usize,
+                    // The first flag clears the question mark.
+                    Mark::Question => max_flags + 1,
+                };
+                (0..flags).map(move |_| neighbour)
+            })
+            .collect()
+    }
 }
-impl Default for Tile {
-    /// Creates a new, uninitialized tile with no 'active values'
-    fn default() -> Self {
-        Tile {
-            is_revealed: false,
-            is_a_mine: false,
-            is_flagged: false,
-            number: None,
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum GameState {
+    Playing,
+    Won,
+    Lost,
+}
+
+/// The settings a game is created with.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct GameSettings {
+    pub(crate) cols: usize,
+    pub(crate) rows: usize,
+    pub(crate) mines: usize,
+    pub(crate) seed: Option<String>,
+    pub(crate) first_click_policy: FirstClickPolicy,
+    pub(crate) question_marks: bool,
+    /// The largest amount of mines a single tile can hold, 1 in a classic game.
+    pub(crate) max_mines_per_tile: u8,
+    /// In a liar game, one number in each region of this many tiles across is off by one. `None` if numbers never lie.
+    pub(crate) liar_region: Option<usize>,
+    /// Which tiles count as neighbours on a square grid.
+    pub(crate) neighbourhood: Neighbourhood,
+    pub(crate) topology: Topology,
+    /// The shape of the board, or `None` for a full rectangle. Has the same dimensions as the board.
+    pub(crate) mask: Option<Mask>,
+}
+
+impl GameSettings {
+    /// An empty board of the shape the settings describe.
+    pub(crate) fn board(&self) -> Board {
+        let mut board = match self.topology {
+            Topology::Cube { layers } => Board::with_layers(self.cols, self.rows, layers),
+            _ => Board::new(self.cols, self.rows),
+        };
+        if let Some(mask) = &self.mask {
+            for (tile, &active) in mask.active.iter().enumerate() {
+                board[tile].set_active(active);
+            }
+        }
+        board
+    }
+
+    /// The most mines the board can hold wherever the first click lands, i.e. the slots of the tiles
+    /// that are left when the first-click policy keeps the most tiles free from mines.
+    pub(crate) fn mine_capacity(&self) -> usize {
+        let board = self.board();
+        let area = ProtectedArea::new(
+            &board,
+            &self.topology,
+            self.neighbourhood,
+            self.first_click_policy,
+        );
+        let most_protected = (0..board.len())
+            .filter(|&tile| board[tile].is_active())
+            .map(|tile| {
+                area.tiles(tile)
+                    .into_iter()
+                    .filter(|&protected| board[protected].is_active())
+                    .count()
+            })
+            .max()
+            .unwrap_or(0);
+        (board.active_tiles() - most_protected) * self.max_mines_per_tile as usize
+    }
+
+    /// Checks settings that come from elsewhere, i.e. from a game code or over the network, before a game is
+    /// started with them. `start_tile` is the tile the game is opened at, if it is decided in advance.
+    /// Returns the part of the settings that is wrong.
+    pub(crate) fn validate(&self, start_tile: Option<usize>) -> Result<(), &'static str> {
+        let size = match self.topology {
+            Topology::Cube { layers } => [layers, self.cols, self.rows],
+            _ => [1, self.cols, self.rows],
+        };
+        // A cube has as many tiles as a square board that is as large as it can be.
+        if size.iter().any(|&side| side == 0 || side > MAX_BOARD_SIDE)
+            || size.iter().product::<usize>() > MAX_BOARD_SIDE * MAX_BOARD_SIDE
+        {
+            return Err("board size");
+        }
+        let tiles = size.iter().product();
+        if !(1..=Tile::MAX_MINES).contains(&self.max_mines_per_tile)
+            || self.liar_region.filter(|region| (3..=5).contains(region)) != self.liar_region
+        {
+            return Err("rules");
+        }
+        // A game with more mines than fit around the protected first click couldn't be started.
+        if self.mines == 0 || self.mines > self.mine_capacity() {
+            return Err("mine count");
+        }
+        if start_tile.filter(|&tile| tile < tiles) != start_tile {
+            return Err("start tile");
         }
+        Ok(())
     }
 }
 
+/// How a game is played: alone, together with other players over the network or at this computer,
+/// watched as a spectator, or as the daily challenge.
+pub(crate) enum GameMode {
+    /// A game played alone.
+    Classic,
+    /// A game played alone and streamed to the spectators watching it.
+    Watched(Spectators),
+    Race(Race),
+    Coop(Coop),
+    /// A game watched as a spectator.
+    Spectate(Spectating),
+    /// A versus game, over the network or between two players sharing this computer.
+    Versus(Versus),
+    Daily(Daily),
+}
+
 /// Contains the data relevant to the game
 pub(crate) struct GameContainer {
-    pub(crate) tile_array: TileArray,
+    pub(crate) board: Board,
     pub(crate) tiles_revealed: usize,
     pub(crate) tiles_flagged: isize,
     pub(crate) game_rows: usize,
     pub(crate) game_cols: usize,
     pub(crate) game_seed: Option<String>,
     pub(crate) game_mines: usize,
+    /// The amount of tiles holding at least one mine, known once the mines have been distributed.
+    pub(crate) mine_tiles: usize,
+    pub(crate) question_marks: bool,
+    pub(crate) max_mines_per_tile: u8,
+    pub(crate) liar_region: Option<usize>,
+    pub(crate) neighbourhood: Neighbourhood,
+    pub(crate) topology: Topology,
+    pub(crate) theme: Theme,
     pub(crate) sprite_batch: SpriteBatch,
     pub(crate) scaled_tile_size: f32,
-    pub(crate) start_time: Option<SystemTime>
-
+    pub(crate) clock: GameClock,
+    pub(crate) state: GameState,
+    /// The mine that lost the game, which is shown as exploded. Several mines may be hit at once by chording,
+    /// in which case it is the first of them.
+    pub(crate) exploded: Option<usize>,
+    pub(crate) stats: GameStats,
+    pub(crate) mode: GameMode,
+    /// Every action performed on the board, together with the player who took it, in the order they were taken.
+    /// Replaying the log on a board with the same settings reproduces the game.
+    pub(crate) log: Vec<(usize, Action)>,
+    /// The states of the board before the latest actions, newest last, for undoing them.
+    pub(crate) history: Vec<Snapshot>,
+    /// Whether the latest hint found nothing that could be deduced. Shown until the next action.
+    pub(crate) no_deduction: bool,
+    pub(crate) keys: KeyBindings,
+    pub(crate) mouse: MouseButtons,
+    /// Whether revealing a revealed number chords it.
+    pub(crate) reveal_chords: bool,
+    /// Whether flagging a revealed number flags its hidden neighbours, when they must all be mines.
+    pub(crate) flag_chords: bool,
 }
 impl GameContainer {
-    /// Creates a new game with the provided settings and seed.
+    /// Creates a new game with the provided settings, drawn with the given theme.
     pub(crate) fn new(
-        mut ctx: &mut Context,
-        game_rows: usize,
-        game_cols: usize,
-        game_mines: usize,
-        game_seed: Option<String>,
+        ctx: &mut Context,
+        settings: GameSettings,
         scaled_tile_size: f32,
+        theme: Theme,
     ) -> GameContainer {
-        let tile_array = vec![vec![Tile::default(); game_rows]; game_cols];
-
-        let img = Image::new(&mut ctx, "/spritesheet.png").expect("Image loading error");
-        let gc = GameContainer {
-            tile_array,
+        let board = settings.board();
+        let img = theme.spritesheet(ctx, &settings.topology).expect("Image loading error");
+        GameContainer {
+            board,
             tiles_revealed: 0,
             tiles_flagged: 0,
-            game_rows,
-            game_cols,
-            game_mines,
-            game_seed,
+            game_rows: settings.rows,
+            game_cols: settings.cols,
+            game_mines: settings.mines,
+            mine_tiles: 0,
+            game_seed: settings.seed,
+            question_marks: settings.question_marks,
+            max_mines_per_tile: settings.max_mines_per_tile,
+            liar_region: settings.liar_region,
+            neighbourhood: settings.neighbourhood,
+            topology: settings.topology,
+            theme,
             sprite_batch: SpriteBatch::new(img),
             scaled_tile_size,
-            start_time: None,
-
-        };
-
-        gc
+            clock: GameClock::default(),
+            state: GameState::Playing,
+            exploded: None,
+            stats: GameStats {
+                first_click_policy: settings.first_click_policy,
+                final_time: None,
+            },
+            mode: GameMode::Classic,
+            log: Vec::new(),
+            history: Vec::new(),
+            no_deduction: false,
+            keys: KeyBindings::default(),
+            mouse: MouseButtons::default(),
+            reveal_chords: false,
+            flag_chords: false,
+        }
     }
 
     /// Distributes the mines randomly or according to the game seed (depending on if "seed" is Some(String) or None).
     ///
-    /// Leaves the upper-left corner free from mines and reveals some tiles,
-    /// because otherwise seeded gameplay would be impossible (since you'd be able to click on a mine on the initial click).
+    /// Every eligible tile is equally likely to hold a mine. When tiles may hold several mines, each eligible tile
+    /// offers `max_mines_per_tile` slots, and the mines are spread uniformly over the slots.
+    /// Which tiles around the first click are kept free from mines is decided by the game's `FirstClickPolicy`,
+    /// so a seeded game is reproduced by the same seed and first click.
     pub(crate) fn distribute_mines(
         &mut self,
-        tile_coords: Option<(usize, usize)>
-    ) {
-        let (clicked_tile_x, clicked_tile_y) = if let Some(tuple) = tile_coords {
-            tuple
-        } else {
-            (0, 0)
+        clicked_tile: usize,
+    ) -> Result<(), MineDistributionError> {
+        let protected_tiles = ProtectedArea::new(
+            &self.board,
+            &self.topology,
+            self.neighbourhood,
+            self.stats.first_click_policy,
+        )
+        .tiles(clicked_tile);
+
+        let mut eligible_slots: Vec<usize> = (0..self.board.len())
+            .filter(|tile| self.board[*tile].is_active() && !protected_tiles.contains(tile))
+            .flat_map(|tile| (0..self.max_mines_per_tile).map(move |_| tile))
+            .collect();
+
+        let mut rng = match &self.game_seed {
+            Some(seed) => seeded_rng(seed),
+            None => ChaCha8Rng::from_entropy(),
         };
 
-        let mut hasher = DefaultHasher::new();
-        let mut token: u64;
-        let mut current_number_of_mines: usize = 0;
-        let (mut x_index, mut y_index) = (0, 0);
-
-        // Uses the MAD-hashing technique to distribute the mines on the game field.
-        // TODO: seeding not really working
-        'outer: loop {
-            'inner: loop {
-                let mut tile = &mut self.tile_array[x_index][y_index];
-                if let Some(ref s) = self.game_seed {
-                    s.hash(&mut hasher);
-                    token = hasher.finish();
-                } else {
-                    token = random::<u64>();
-                }
+        self.mine_tiles += self
+            .board
+            .place_mines(&mut eligible_slots, self.game_mines, &mut rng)?;
 
-                if (token.overflowing_mul(23).0 + 7) % 53 == 0
-                    && !tile.is_a_mine
-                    && (self.game_seed.is_some() && x_index > 1 && y_index > 1
-                        || (self.game_seed.is_none()
-                            && !((x_index as isize - clicked_tile_x as isize).abs() < 2
-                                && (y_index as isize - clicked_tile_y as isize).abs() < 2)))
-                {
-                    tile.is_a_mine = true;
-                    current_number_of_mines += 1;
-                    if current_number_of_mines == self.game_mines {
-                        break 'outer;
-                    }
-                }
-                y_index += 1;
-                if y_index == self.game_rows {
-                    y_index = 0;
-                    break 'inner;
-                }
-            }
-            x_index += 1;
-            if x_index == self.game_cols {
-                x_index = 0;
+        // Sets the number for each tile (the amount of mines surrounding the tile)
+        self.board.set_numbers(&self.topology, self.neighbourhood);
+
+        if let Some(region_size) = self.liar_region {
+            self.add_lies(&mut rng, region_size);
+        }
+
+        Ok(())
+    }
+
+    /// Makes one numbered tile in each `region_size`x`region_size` region of the board show a number that is off by one.
+    ///
+    /// The real numbers are kept, so revealing and winning work as usual. A lying 1 always shows a 2,
+    /// so that a lie never looks like an empty tile.
+    fn add_lies<R: Rng + ?Sized>(&mut self, rng: &mut R, region_size: usize) {
+        // Ordered by region, so that seeded games get the same lies.
+        let mut regions: BTreeMap<(i64, i64), Vec<usize>> = BTreeMap::new();
+        for (tile_index, tile) in self.board.iter() {
+            if tile.is_active() && tile.number().is_some() {
+                let region = self
+                    .topology
+                    .liar_region(&self.board, tile_index, region_size);
+                regions.entry(region).or_default().push(tile_index);
             }
         }
 
-        // Sets the number for each tile (the amount of mines surrounding the tile)
-        for x in 0..self.game_cols {
-            for y in 0..self.game_rows {
-                self.set_tile_number((x, y));
+        for tiles in regions.values() {
+            if let Some(&tile_index) = tiles.choose(rng) {
+                let tile = &mut self.board[tile_index];
+                let lie = if tile.number() == Some(1) || rng.gen() {
+                    1
+                } else {
+                    -1
+                };
+                tile.set_lie(lie);
             }
         }
+    }
 
+    /// The size of the board on the screen, in pixels.
+    pub(crate) fn board_size(&self) -> (f32, f32) {
+        self.topology
+            .board_size(self.game_cols, self.game_rows, self.scaled_tile_size)
+    }
 
-        self.start_time = Some(std::time::SystemTime::now());
+    /// Starts the game in the given mode. Games played together and daily challenges open the board
+    /// at their start tile, which also starts the clock.
+    pub(crate) fn start(&mut self, mode: GameMode) -> Result<(), MineDistributionError> {
+        match mode {
+            GameMode::Race(race) => self.start_race(race),
+            GameMode::Coop(coop) => self.start_coop(coop),
+            GameMode::Daily(daily) => self.start_daily(daily),
+            mode => {
+                self.mode = mode;
+                Ok(())
+            }
+        }
     }
 
+    /// The versus game this game is, if two players take turns on the board.
+    pub(crate) fn versus(&self) -> Option<&Versus> {
+        match &self.mode {
+            GameMode::Versus(versus) => Some(versus),
+            _ => None,
+        }
+    }
 
-    // TODO: https://magnushoff.com/articles/minesweeper/
-   
+    pub(crate) fn is_paused(&self) -> bool {
+        self.clock.is_paused()
+    }
 
-    /// Reveals the tile at the given coordinates.
-    pub(crate) fn reveal_tile_at(&mut self, (tile_x, tile_y): (usize, usize)) {
-        let tile = &self.tile_array[tile_x][tile_y];
-        if !tile.is_revealed && !tile.is_flagged {
-            if tile.is_a_mine {
-                self._lose(&tile);
-                return;
-            } else {
-                self.reveal_nearby((tile_x, tile_y));
-                self.check_if_win();
-            }
+    /// Pauses or resumes the game. Only a game whose clock is running can be paused,
+    /// and games played over the network or watched by spectators can't be paused.
+    pub(crate) fn toggle_pause(&mut self) {
+        match &self.mode {
+            GameMode::Classic | GameMode::Daily(_) => {}
+            GameMode::Versus(versus) if !versus.is_network_game() => {}
+            _ => return,
+        }
+        if self.clock.is_paused() {
+            self.clock.resume();
+        } else {
+            self.clock.pause();
         }
     }
 
-    /// Toggles if the tile is flagged or not
-    pub(crate) fn toggle_flag_at(&mut self, (tile_x, tile_y): (usize, usize)) {
-        let tile = &mut self.tile_array[tile_x][tile_y];
-        if !tile.is_revealed {
-            if tile.is_flagged{
-                tile.is_flagged = false;
-                self.tiles_flagged -= 1;
-            }else{
-                tile.is_flagged = true;
-                self.tiles_flagged += 1;
+    /// Takes an action of this player. In a co-op game hosted by another player, the action is sent to the host,
+    /// and its outcome arrives with the next changes of the board.
+    /// In a versus game, the action is taken for the player whose turn it is, if they are at this computer.
+    /// In a game played alone, the board before the action is kept, so that the action can be undone.
+    pub(crate) fn act(&mut self, action: Action) -> Result<(), MineDistributionError> {
+        self.no_deduction = false;
+        let player = match &mut self.mode {
+            GameMode::Coop(coop) if !coop.is_host => {
+                let player = coop.player;
+                coop.connection.send(0, &Message::Action { player, action });
+                return Ok(());
             }
+            GameMode::Versus(versus) if !versus.is_local_turn() => return Ok(()),
+            GameMode::Versus(versus) => versus.turn,
+            _ => 0,
+        };
+        self.send_versus_action(player, action);
+        let snapshot = self.snapshot();
+        self.record(player, action)?;
+        self.keep_snapshot(snapshot);
+        self.sync_coop();
+        self.report_progress();
+        Ok(())
+    }
+
+    /// Logs an action taken by the given player and performs it, streaming it to any spectators.
+    pub(crate) fn record(
+        &mut self,
+        player: usize,
+        action: Action,
+    ) -> Result<(), MineDistributionError> {
+        self.log.push((player, action));
+        let result = self.perform(action);
+        let state = self.state_message();
+        if let GameMode::Watched(spectators) = &mut self.mode {
+            spectators.stream(player, action, &state);
         }
+        result
     }
 
-    pub(crate) fn chord_at(&mut self, (tile_x, tile_y): (usize, usize)) {
-        let tile = self.tile_array[tile_x][tile_y];
-        if tile.is_revealed && tile.number.is_some() {
-            let mut acc: u8 = 0;
-            self.map_tile_and_surrounding(
-                (tile_x, tile_y),
-                |sself: &mut Self, (x, y): (usize, usize)| {
-                    if sself.tile_array[x][y].is_flagged {
-                        acc += 1;
-                    }
-                },
-            );
-
-            if tile.number == Some(acc) {
-                self.map_tile_and_surrounding(
-                    (tile_x, tile_y),
-                    |sself: &mut Self, (x, y): (usize, usize)| sself.reveal_tile_at((x, y)), //TODO: Bör kunna ersättas med GameContainer::reveal_tile_at ist för anonym funktion
-                );
-            }
+    /// The time the game has been running for. In a co-op game hosted by another player, this is the host's clock,
+    /// and for a spectator it is the clock of the watched game.
+    pub(crate) fn elapsed(&self) -> Duration {
+        let (elapsed, received) = match &self.mode {
+            GameMode::Coop(coop) if !coop.is_host => coop.host_elapsed,
+            GameMode::Spectate(spectating) => spectating.player_elapsed,
+            _ => return self.clock.elapsed(),
+        };
+        // The received time is extrapolated until the next update arrives.
+        if self.state == GameState::Playing && elapsed > Duration::default() {
+            elapsed + received.elapsed()
+        } else {
+            elapsed
         }
     }
 
-    /// Recursively reveals tiles around the argument tile if it is empty `(is_a_mine == False && number == None)`
-    fn reveal_nearby(&mut self, (tile_x, tile_y): (usize, usize)) {
-        let mut tile: &mut Tile = &mut self.tile_array[tile_x][tile_y];
-        if !tile.is_revealed && !tile.is_flagged {
-            match tile.number {
-                None if tile.is_a_mine => {
-                    return;
-                }
-                None => {
-                    tile.is_revealed = true;
-                    self.map_tile_and_surrounding((tile_x, tile_y), GameContainer::reveal_nearby);
-                }
-                Some(_) => {
-                    tile.is_revealed = true;
-                }
+    /// Moves this player's cursor, given as a position on the screen, and lets the other players know.
+    pub(crate) fn move_cursor(&mut self, (x, y): (f32, f32)) {
+        let position = (x / self.scaled_tile_size, y / self.scaled_tile_size);
+        if let GameMode::Coop(coop) = &mut self.mode {
+            let player = coop.player;
+            coop.set_cursor(player, position);
+            let message = Message::Cursor {
+                player,
+                x: position.0,
+                y: position.1,
+            };
+            if coop.is_host {
+                coop.connection.broadcast(&message);
+            } else {
+                coop.connection.send(0, &message);
             }
-            self.tiles_revealed += 1;
         }
     }
 
-    /// Sets the number for each non-mine tile
-    fn set_tile_number(&mut self, (tile_x, tile_y): (usize, usize)) {
-        if self.tile_array[tile_x][tile_y].is_a_mine {
-            let add_if_not_mine = |sself: &mut Self, (x, y): (usize, usize)| {
-                let mut tile = &mut sself.tile_array[x][y];
-                if !tile.is_a_mine {
-                    tile.number = if tile.number.is_none() {
-                        Some(1)
-                    } else {
-                        Some(tile.number.unwrap() + 1)
-                    }
-                }
+    /// Performs an action on the board. The mines are distributed on the first left-click of the game.
+    ///
+    /// Actions on tiles they can't affect, e.g. revealing a flagged tile, do nothing.
+    /// In a versus game, revealing is the only action.
+    pub(crate) fn perform(&mut self, action: Action) -> Result<(), MineDistributionError> {
+        if self.state != GameState::Playing {
+            return Ok(());
+        }
+        if self.versus().is_some() {
+            return match action {
+                Action::Reveal(tile_index) => self.take_turn(tile_index),
+                _ => Ok(()),
             };
-            self.map_tile_and_surrounding((tile_x, tile_y), add_if_not_mine);
         }
+        match action {
+            Action::Reveal(tile_index)
+                if self.tiles_revealed == 0 && !self.board[tile_index].is_flagged() =>
+            {
+                self.distribute_mines(tile_index)?;
+                self.reveal_tile_at(tile_index);
+            }
+            Action::Reveal(tile_index) => self.reveal_tile_at(tile_index),
+            Action::Flag(tile_index) => self.toggle_flag_at(tile_index),
+            Action::Chord(tile_index) => self.chord_at(tile_index),
+        }
+        Ok(())
     }
 
-    /// A higher-order function that applies the given function to the given tile and to all of the tiles surrounding it.
-    fn map_tile_and_surrounding<T>(&mut self, (tile_x, tile_y): (usize, usize), mut func: T)
-    where
-        T: FnMut(&mut Self, (usize, usize)),
-    {
-        for xd in -1..=1 {
-            for yd in -1..=1 {
-                // Makes sure that it doesn't go outside of the tile_array index bounds
-                let (x, y) = (tile_x as i16 + xd, tile_y as i16 + yd);
-                if x >= 0 && x < self.game_cols as i16 && y >= 0 && y < self.game_rows as i16 {
-                    func(self, (x as usize, y as usize))
-                }
+    // TODO: https://magnushoff.com/articles/minesweeper/
+   
+
+    /// Reveals the tile with the given index.
+    pub(crate) fn reveal_tile_at(&mut self, tile_index: usize) {
+        match self.reveal(tile_index) {
+            Revealed::Nothing => {}
+            Revealed::Mine => {
+                self.clock.start();
+                self.exploded.get_or_insert(tile_index);
+                self.lose();
             }
+            Revealed::Tiles(revealed) => {
+                self.clock.start();
+                self.tiles_revealed += revealed;
+                self.check_if_win();
+            }
+        }
+    }
+
+    /// Cycles the mark of the tile, with as many flags as a tile can hold mines.
+    pub(crate) fn toggle_flag_at(&mut self, tile_index: usize) {
+        self.tiles_flagged += self.cycle_mark(tile_index, self.max_mines_per_tile, self.question_marks);
+    }
+
+    /// Reveals the tiles surrounding a revealed number if it is surrounded by as many flags as its number.
+    pub(crate) fn chord_at(&mut self, tile_index: usize) {
+        for neighbour in self.chord_targets(tile_index) {
+            self.reveal_tile_at(neighbour);
         }
     }
 
-    fn _lose(&self, clicked_tile: &Tile) {
-        todo!()
+    /// The flags that mark every hidden neighbour of a revealed number as a mine, if the number leaves no doubt.
+    pub(crate) fn flag_chord_actions(&self, tile_index: usize) -> Vec<Action> {
+        self.flag_chord_targets(tile_index, self.max_mines_per_tile)
+            .into_iter()
+            .map(Action::Flag)
+            .collect()
+    }
+
+    pub(crate) fn lose(&mut self) {
+        self.state = GameState::Lost;
+        self.stats.final_time = Some(self.clock.stop());
     }
 
-    fn check_if_win(&self) {
-        if self.tiles_revealed == self.game_cols * self.game_rows - self.game_mines {
-            todo!()
+    /// A versus game isn't won by clearing the board, but by claiming the most mines.
+    fn check_if_win(&mut self) {
+        if self.state == GameState::Playing
+            && self.versus().is_none()
+            && self.tiles_revealed == self.board.active_tiles() - self.mine_tiles
+        {
+            self.state = GameState::Won;
+            self.stats.final_time = Some(self.clock.stop());
         }
     }
 }
+
+impl Playfield for GameContainer {
+    type Pos = usize;
+
+    fn tile(&self, tile_index: usize) -> Tile {
+        self.board[tile_index]
+    }
+
+    fn tile_mut(&mut self, tile_index: usize) -> &mut Tile {
+        &mut self.board[tile_index]
+    }
+
+    /// The neighbours as decided by the game's `Topology` and `Neighbourhood`. The numbers and flood fill
+    /// of `Board` skip disabled tiles the same way, so they all agree on the neighbours.
+    fn neighbours(&self, tile_index: usize) -> Vec<usize> {
+        let mut neighbours = self
+            .topology
+            .neighbours(&self.board, tile_index, self.neighbourhood);
+        neighbours.retain(|&neighbour| self.board[neighbour].is_active());
+        neighbours
+    }
+
+    fn reveal_area(&mut self, tile_index: usize) -> usize {
+        self.board
+            .reveal_area(tile_index, &self.topology, self.neighbourhood)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_safe_area_wraps_around_a_torus() {
+        let board = Board::new(5, 4);
+        let area = ProtectedArea::new(
+            &board,
+            &Topology::Torus,
+            Neighbourhood::Knight,
+            FirstClickPolicy::SafeArea,
+        );
+        // The corner and the tiles around it, on the other sides of the board as well.
+        let corner = [(4, 3), (0, 3), (1, 3), (4, 0), (0, 0), (1, 0), (4, 1), (0, 1), (1, 1)];
+        let mut expected: Vec<usize> = corner
+            .iter()
+            .map(|&coords| board.index_of(coords))
+            .collect();
+        expected.sort_unstable();
+        assert_eq!(area.tiles(board.index_of((0, 0))), expected);
+    }
+}
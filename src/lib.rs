@@ -0,0 +1,8 @@
+//! The board, the ways its tiles can neighbour each other, the randomness of seeded games and what can be
+//! deduced from the numbers.
+//! They don't depend on the window, so that they can be benchmarked and tested on their own.
+
+pub mod board;
+pub mod seed;
+pub mod solver;
+pub mod topology;
@@ -1,114 +1,231 @@
 use std::process::exit;
 
-use crate::game::GameContainer;
+use crate::{
+    board::{MineDistributionError, Tile},
+    endless::EndlessGame,
+    game::{FirstClickPolicy, GameContainer, GameMode, GameSettings, MAX_BOARD_SIDE},
+    mask::Mask,
+    config::{Command, Config},
+    coop::Coop,
+    daily::{Daily, DailyOutcome},
+    net::{Lobby, Message, NetworkMode, SharedBoard},
+    race::Race,
+    spectate::{Spectators, SPECTATOR_PORT},
+    theme::Theme,
+    topology::{Neighbourhood, Topology},
+    versus::Versus,
+};
 extern crate dialoguer;
 extern crate rand;
 
+mod assist;
+mod clock;
+mod code;
+mod config;
+mod coop;
+mod daily;
+mod endless;
 mod event_handler;
 mod game;
+mod mask;
+mod net;
+mod race;
+mod spectate;
+mod theme;
+mod versus;
 
 use colored::*;
+use rustsweeper::{board, seed, solver, topology};
 use ggez::{
     conf::WindowMode,
-    event,
+    event::{self, EventsLoop},
     graphics::{self},
+    Context,
 };
 use rand::{distributions::Alphanumeric, Rng};
 
 const DEFAULT_TILE_SIZE: f32 = 40.0;
-const SPRITESHEET_WIDTH: f32 = 440.0;
+
+/// A difficulty preset: name, columns, rows, mines and the colour of its menu entry.
+type Preset = (&'static str, usize, usize, usize, (u8, u8, u8));
+
+const SQUARE_PRESETS: [Preset; 7] = [
+    ("Novice", 9, 9, 10, (20, 220, 0)),
+    ("Versed", 16, 16, 40, (147, 230, 0)),
+    ("Expert", 30, 16, 99, (212, 230, 0)),
+    ("Master", 40, 24, 250, (255, 200, 0)),
+    ("Savant", 60, 35, 691, (255, 148, 0)),
+    ("Legend", 82, 44, 1334, (255, 88, 0)),
+    ("Unreal", 120, 50, 2520, (174, 0, 0)),
+];
+
+/// Hexagonal tiles only have 6 neighbours, so the same density makes for an easier game than on a square board.
+const HEX_PRESETS: [Preset; 5] = [
+    ("Novice", 9, 9, 10, (20, 220, 0)),
+    ("Versed", 16, 16, 36, (147, 230, 0)),
+    ("Expert", 30, 16, 86, (212, 230, 0)),
+    ("Master", 40, 24, 202, (255, 148, 0)),
+    ("Legend", 60, 35, 546, (174, 0, 0)),
+];
+
+/// Triangles border 12 other tiles, so they need lower densities, and twice the columns to cover the same width.
+const TRIANGLE_PRESETS: [Preset; 5] = [
+    ("Novice", 17, 9, 12, (20, 220, 0)),
+    ("Versed", 31, 16, 50, (147, 230, 0)),
+    ("Expert", 59, 16, 110, (212, 230, 0)),
+    ("Master", 79, 24, 265, (255, 148, 0)),
+    ("Legend", 119, 35, 700, (174, 0, 0)),
+];
+
+/// A cube has 26 neighbours per tile, so even low densities make for hard games.
+/// Cubes are as deep as they are wide, so the columns are also the amount of layers.
+const CUBE_PRESETS: [Preset; 4] = [
+    ("Novice", 4, 4, 4, (20, 220, 0)),
+    ("Versed", 6, 6, 18, (147, 230, 0)),
+    ("Expert", 8, 8, 50, (255, 148, 0)),
+    ("Legend", 10, 10, 120, (174, 0, 0)),
+];
 
 fn main() {
-    let (game_cols, game_rows, game_mines): (usize, usize, usize);
-    let game_seed: Option<String>;
-    let default_settings = [
-        "Novice [9x9] (81 tiles, 12% mines)".color(Color::TrueColor {
-            r: 20,
-            g: 220,
-            b: 0,
-        }),
-        "Versed [16x16] (256 tiles, 16% mines)".color(Color::TrueColor {
-            r: 147,
-            g: 230,
-            b: 0,
-        }),
-        "Expert [30x16] (480 tiles, 21% mines)".color(Color::TrueColor {
-            r: 212,
-            g: 230,
-            b: 0,
-        }),
-        "Master [40x24] (960 tiles, 26% mines)".color(Color::TrueColor {
-            r: 255,
-            g: 200,
-            b: 0,
-        }),
-        "Savant [60x35] (2160 tiles, 32% mines)".color(Color::TrueColor {
-            r: 255,
-            g: 148,
-            b: 0,
-        }),
-        "Legend [82x44] (3608 tiles, 37% mines)".color(Color::TrueColor {
-            r: 255,
-            g: 88,
-            b: 0,
-        }),
-        "Unreal [120x50] (6000 tiles, 42% mines)".color(Color::TrueColor { r: 174, g: 0, b: 0 }),
-    ];
+    let mut game_seed: Option<String>;
+    let mut config = Config::load();
+
+    // `--spectate <address>` watches a game played elsewhere instead of starting one.
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(position) = args.iter().position(|arg| arg == "--spectate") {
+        return match args.get(position + 1) {
+            Some(address) => spectate_game(address, &mut config),
+            None => println!("Usage: --spectate <address>, e.g. --spectate 192.168.0.2:{}", SPECTATOR_PORT),
+        };
+    }
 
     loop {
-        match dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
-            .items(&default_settings)
-            .default(0)
-            .with_prompt("\nPlease select one of game settings below using the arrow keys.\nConfirm with Enter, exit with Q.")
-            .interact_opt()
-        {
-            Ok(option) => {
-                if let Some(alt) = option {
-                    let (cols, rows, mines) = match alt {
-                        0 => {
-                            (9, 9, 10)
-                        }
-                        1 => {
-                            (16, 16, 40)
-                        }
-                        2 => {
-                            (30, 16, 99)
-                        }
-                        3 => {
-                            (40, 24, 250)
-                        }
-                        4 => {
-                            (60, 35, 691)
-                        }
-                        5 => {
-                            (82, 44, 1334)
-                        }
-                        6 => {
-                            (120, 50, 2520)
-                        }
-                        _ => {
-                            println!(
-                                "You need to choose one of the options. Please try again or press Q to exit."
-                            );
-                            continue;
-                        }
-                    };
-                    game_cols = cols;
-                    game_rows = rows;
-                    game_mines = mines;
-                    break;
-                } else {
-                    exit(0);
+        match select(
+            "\nPlease select a game mode using the arrow keys.\nConfirm with Enter, exit with Q.",
+            &[
+                "Classic",
+                "Play a game code",
+                "Endless",
+                "Daily challenge",
+                "Versus on one computer",
+                "Host a network game",
+                "Join a network game",
+                "Settings",
+            ],
+        ) {
+            1 => return play_code(&mut config),
+            2 => return play_endless(&mut config),
+            3 => return play_daily(&mut config),
+            4 => return play_versus(&mut config),
+            5 => return host_network_game(&mut config),
+            6 => return join_network_game(&mut config),
+            7 => edit_settings(&mut config),
+            _ => break,
+        }
+    }
+
+    let topologies = [
+        Topology::Square,
+        Topology::Torus,
+        Topology::Hex,
+        Topology::Triangle,
+        Topology::Cube { layers: 0 },
+        Topology::Graph {
+            positions: Vec::new(),
+            neighbours: Vec::new(),
+        },
+    ];
+    let mut topology = match &topologies[select(
+        "Please select the shape of the board.",
+        &topologies,
+    )] {
+        Topology::Graph { .. } => loop {
+            let path: String = dialoguer::Input::new()
+                .with_prompt("Please enter the path of the board file")
+                .interact()
+                .unwrap();
+            match Topology::load_graph(std::path::Path::new(path.trim())) {
+                Ok(graph) => break graph,
+                Err(e) => println!("{}", e),
+            }
+        },
+        topology => topology.clone(),
+    };
+
+    let presets: &[Preset] = match topology {
+        Topology::Hex => &HEX_PRESETS,
+        Topology::Triangle => &TRIANGLE_PRESETS,
+        Topology::Cube { .. } => &CUBE_PRESETS,
+        _ => &SQUARE_PRESETS,
+    };
+    let is_cube = matches!(topology, Topology::Cube { .. });
+    let mut preset_names: Vec<ColoredString> = presets
+        .iter()
+        .map(|&(name, cols, rows, mines, (r, g, b))| {
+            let (tiles, dimensions) = if is_cube {
+                (cols * rows * cols, format!("{}x{}x{}", cols, rows, cols))
+            } else {
+                (cols * rows, format!("{}x{}", cols, rows))
+            };
+            format!(
+                "{} [{}] ({} tiles, {}% mines)",
+                name,
+                dimensions,
+                tiles,
+                (mines * 100 + tiles / 2) / tiles
+            )
+            .color(Color::TrueColor { r, g, b })
+        })
+        .collect();
+    if !is_cube {
+        let (cols, rows, mines) = config.custom_size;
+        preset_names.push(format!("Custom size (last: {}x{}, {} mines)", cols, rows, mines).normal());
+        preset_names.push("Custom shape from mask file".normal());
+    }
+    let preferred = presets
+        .iter()
+        .position(|&(name, ..)| name == config.difficulty)
+        .unwrap_or(0);
+
+    let mut mask = None;
+    let (game_cols, game_rows, game_mines) = if let Topology::Graph { positions, .. } = &topology {
+        // A graph has no presets, its tiles are stored in a single row.
+        (positions.len(), 1, ask_mines(positions.len()))
+    } else {
+        match select_with_default("Please select one of the game settings below.", &preset_names, preferred) {
+            alt if alt < presets.len() => {
+                let (name, cols, rows, mines, _) = presets[alt];
+                config.difficulty = name.to_string();
+                if is_cube {
+                    topology = Topology::Cube { layers: cols };
                 }
+                (cols, rows, mines)
             }
-            Err(_) => {
-                println!(
-                    "You need to choose one of the options. Please try again or press Q to exit."
-                );
-                continue;
+            alt if alt == presets.len() => {
+                let (last_cols, last_rows, last_mines) = config.custom_size;
+                let cols = ask_number("Please enter the amount of columns", last_cols, 2..=MAX_BOARD_SIDE);
+                let rows = ask_number("Please enter the amount of rows", last_rows, 2..=MAX_BOARD_SIDE);
+                let mines = ask_number("Please enter the amount of mines", last_mines.min(cols * rows - 1), 1..=cols * rows - 1);
+                config.custom_size = (cols, rows, mines);
+                (cols, rows, mines)
+            }
+            _ => {
+                let loaded = loop {
+                    let path: String = dialoguer::Input::new()
+                        .with_prompt("Please enter the path of the mask (a text file or an image)")
+                        .interact()
+                        .unwrap();
+                    match Mask::load(std::path::Path::new(path.trim())) {
+                        Ok(mask) => break mask,
+                        Err(e) => println!("{}", e),
+                    }
+                };
+                let dimensions = (loaded.cols, loaded.rows, ask_mines(loaded.active_cells()));
+                mask = Some(loaded);
+                dimensions
             }
         }
-    }
+    };
 
     loop {
         match dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
@@ -125,19 +242,10 @@ fn main() {
                             break;
                         }
                         1 => {
-                            let random_seed = format!(
-                                "{}",
-                                rand::thread_rng()
-                                    .sample_iter(Alphanumeric)
-                                    .take(20)
-                                    .map(char::from)
-                                    .collect::<String>()
-                            );
-                            println!("WARNING: Due to the nature of seeded generation, the first click is not guaranteed to be safe. To still make it playable, the game makes sure that the top left corner is safe on a seeded game.");
                             game_seed = Some(
                                 dialoguer::Input::new()
                                     .with_prompt("Please enter game seed (press Enter for random)")
-                                    .default(random_seed)
+                                    .default(random_seed())
                                     .show_default(false)
                                     .interact()
                                     .unwrap(),
@@ -164,17 +272,602 @@ fn main() {
         }
     }
 
+    let first_click_policies = [
+        FirstClickPolicy::SafeArea,
+        FirstClickPolicy::Opening,
+        FirstClickPolicy::SafeTile,
+        FirstClickPolicy::Unprotected,
+    ];
+    let first_click_policy = first_click_policies[select(
+        "How should the first click be protected from mines?",
+        &first_click_policies,
+    )];
+
+    let question_marks = dialoguer::Confirm::with_theme(&dialoguer::theme::ColorfulTheme::default())
+        .with_prompt("Do you want right-clicks to also cycle through question marks?")
+        .default(config.question_marks)
+        .interact()
+        .unwrap_or(false);
+    config.question_marks = question_marks;
+    save_config(&config);
+
+    // Every neighbour of a tile may be full of mines, and the tile's number has to fit in the tile.
+    // The neighbourhood is chosen later, so the largest one is assumed.
+    let most_neighbours = topology.most_neighbours(Neighbourhood::Radius2);
+    let allowed = (1..=Tile::MAX_MINES as usize)
+        .filter(|mines| most_neighbours * mines <= Tile::MAX_NUMBER as usize)
+        .count();
+    let max_mines_per_tile = select(
+        "How many mines may a single tile hold?",
+        &["1 (classic)", "Up to 2", "Up to 3"][..allowed],
+    ) as u8
+        + 1;
+
+    let liar_region = match select(
+        "Should some numbers lie? A lying number is off by one.",
+        &[
+            "No, numbers never lie",
+            "One lie per 3x3 region",
+            "One lie per 4x4 region",
+            "One lie per 5x5 region",
+        ],
+    ) {
+        0 => None,
+        alt => Some(alt + 2),
+    };
+
+    let neighbourhood = match topology {
+        Topology::Square | Topology::Torus => {
+            let neighbourhoods = [
+                Neighbourhood::Adjacent,
+                Neighbourhood::Knight,
+                Neighbourhood::Radius2,
+            ];
+            neighbourhoods[select(
+                "Which tiles should the numbers count the mines of?",
+                &neighbourhoods,
+            )]
+        }
+        _ => Neighbourhood::Adjacent,
+    };
+
+    // Spectators replay the game from its seed, so games on loaded boards can't be watched.
+    let watchable = mask.is_none() && !matches!(topology, Topology::Graph { .. });
+    let allow_spectators = watchable
+        && dialoguer::Confirm::with_theme(&dialoguer::theme::ColorfulTheme::default())
+            .with_prompt("Do you want to let others watch this game?")
+            .default(false)
+            .interact()
+            .unwrap_or(false);
+    // A random game gets a random seed, so that it can be shared by its game code, and replayed by spectators.
+    if game_seed.is_none() {
+        game_seed = Some(random_seed());
+    }
+
+    let mut settings = GameSettings {
+        cols: game_cols,
+        rows: game_rows,
+        mines: game_mines,
+        seed: game_seed,
+        first_click_policy,
+        question_marks,
+        max_mines_per_tile,
+        liar_region,
+        neighbourhood,
+        topology,
+        mask,
+    };
+    // The first-click protection and the mines per tile decide how many mines fit, so the amount is checked here.
+    let capacity = settings.mine_capacity();
+    if settings.mines > capacity && capacity > 0 {
+        println!(
+            "With this first-click protection, the board has room for at most {} mines.",
+            capacity
+        );
+        settings.mines = ask_number("Please enter the amount of mines", capacity, 1..=capacity);
+    }
+    let game_mode = if allow_spectators {
+        let port: u16 = dialoguer::Input::new()
+            .with_prompt("Please enter the port spectators connect to")
+            .default(SPECTATOR_PORT)
+            .interact()
+            .unwrap();
+        match Spectators::host(port, &settings) {
+            Ok(spectators) => {
+                println!("Spectators can watch with --spectate <your address>:{}", port);
+                GameMode::Watched(spectators)
+            }
+            Err(e) => {
+                println!("Could not let spectators in: {}", e);
+                GameMode::Classic
+            }
+        }
+    } else {
+        GameMode::Classic
+    };
+    if let Some(code) = settings.to_code() {
+        println!("Game code: {}", code);
+    }
+    run_game(settings, game_mode, &mut config);
+}
+
+/// Opens the window for a game with the given settings and runs it in the given mode.
+/// The window is placed and the game is controlled as configured, and the position of the window is remembered
+/// for the next game.
+fn run_game(settings: GameSettings, game_mode: GameMode, config: &mut Config) {
+    // A game with more mines than fit on the board would end at the first click, so it isn't started.
+    let capacity = settings.mine_capacity();
+    if settings.mines > capacity {
+        println!(
+            "{}",
+            MineDistributionError::TooManyMines {
+                requested: settings.mines,
+                available: capacity,
+            }
+        );
+        return;
+    }
+
     // Scales the size of the tiles depending on game settings, this in order to make sure that the game fits on the screen.
-    let scaled_tile_size = f32::min(DEFAULT_TILE_SIZE, 1800.0 / game_cols as f32)
-        .min(1000.0 / game_rows as f32)
+    let (unscaled_width, unscaled_height) =
+        settings.topology.board_size(settings.cols, settings.rows, 1.0);
+    let scaled_tile_size = f32::min(config.tile_size, 1800.0 / unscaled_width)
+        .min(1000.0 / unscaled_height)
         .floor();
+    let (board_width, board_height) =
+        settings.topology.board_size(settings.cols, settings.rows, scaled_tile_size);
+    let sidebar_width = match game_mode {
+        GameMode::Classic | GameMode::Watched(_) | GameMode::Daily(_) => 0.0,
+        _ => race::SIDEBAR_WIDTH,
+    };
+
+    let (mut ctx, mut event_loop) = build_context(board_width + sidebar_width, board_height + 15.0);
+    restore_window_position(&ctx, config);
+
+    let mut game = GameContainer::new(&mut ctx, settings, scaled_tile_size, load_theme(config));
+    game.keys = config.keys.clone();
+    game.mouse = config.mouse.clone();
+    game.reveal_chords = config.reveal_chords;
+    game.flag_chords = config.flag_chords;
+    if let Err(e) = game.start(game_mode) {
+        println!("{}", e);
+        return;
+    }
+    println!("Game initialized successfully.");
 
+    match event::run(&mut ctx, &mut event_loop, &mut game) {
+        Ok(_) => {
+            println!("Game exited cleanly.");
+        }
+        Err(e) => println!("Error occured: {}", e),
+    }
+    remember_window_position(&ctx, config);
+}
+
+/// Moves the window to where it was when the last game was closed, if that is known.
+fn restore_window_position(ctx: &Context, config: &Config) {
+    let window = graphics::window(ctx);
+    // ggez doesn't expose winit's position type, so the window's current position is moved instead.
+    if let (Some((x, y)), Some(mut position)) = (config.window_position, window.get_position()) {
+        position.x = x;
+        position.y = y;
+        window.set_position(position);
+    }
+}
+
+/// Stores the position of the window in the config file, so that the next game opens in the same place.
+fn remember_window_position(ctx: &Context, config: &mut Config) {
+    if let Some(position) = graphics::window(ctx).get_position() {
+        config.window_position = Some((position.x, position.y));
+        save_config(config);
+    }
+}
+
+/// Saves the config file, and lets the user know if that fails. The game works the same without it.
+fn save_config(config: &Config) {
+    if let Err(e) = config.save() {
+        println!("Could not save the settings: {}", e);
+    }
+}
+
+/// Loads the theme chosen in the settings. A theme that can't be loaded is reported, and the dark theme is used.
+fn load_theme(config: &Config) -> Theme {
+    Theme::load(&config.theme).unwrap_or_else(|e| {
+        println!("{}. Using the dark theme.", e);
+        Theme::load("dark").expect("The built-in dark theme is missing")
+    })
+}
+
+/// Hosts a game on the local network, which starts once the host decides that everyone has joined.
+fn host_network_game(config: &mut Config) {
+    let modes = [NetworkMode::Race, NetworkMode::Coop, NetworkMode::Versus];
+    let mode = modes[select("How do you want to play together?", &modes)];
+    let name = ask_name();
+    let port: u16 = dialoguer::Input::new()
+        .with_prompt("Please enter the port to host the game on")
+        .default(net::DEFAULT_PORT)
+        .interact()
+        .unwrap();
+    let preset_names: Vec<String> = SQUARE_PRESETS
+        .iter()
+        .map(|&(name, cols, rows, mines, _)| format!("{} [{}x{}] ({} mines)", name, cols, rows, mines))
+        .collect();
+    let (_, cols, rows, mines, _) =
+        SQUARE_PRESETS[select("Please select the board.", &preset_names)];
+
+    let lobby = match Lobby::host(port) {
+        Ok(lobby) => lobby,
+        Err(e) => {
+            println!("Could not host the game: {}", e);
+            exit(1);
+        }
+    };
+    println!("Waiting for players to join on port {}.", port);
+    let start = dialoguer::Confirm::new()
+        .with_prompt("Start the game once everyone has joined")
+        .default(true)
+        .interact()
+        .unwrap_or(false);
+    if !start {
+        exit(0);
+    }
+
+    let (player_names, mut connection) = lobby.start();
+    if mode == NetworkMode::Versus && player_names.len() != 1 {
+        println!("A versus game is played by exactly two players, but {} joined.", player_names.len() + 1);
+        exit(1);
+    }
+    let board = SharedBoard {
+        mode,
+        cols,
+        rows,
+        mines,
+        start_tile: (rows / 2) * cols + cols / 2,
+        seed: random_seed(),
+    };
+    connection.broadcast(&Message::Start(board.clone()));
+    for player in 1..=player_names.len() {
+        // Peer i is player i + 1, since the host is player 0.
+        connection.send(player - 1, &Message::Welcome { player });
+    }
+
+    let network_game = match mode {
+        NetworkMode::Race => {
+            GameMode::Race(Race::host(connection, name, player_names, board.start_tile))
+        }
+        NetworkMode::Coop => {
+            let names = std::iter::once(name).chain(player_names).collect();
+            GameMode::Coop(Coop::host(connection, names, board.start_tile))
+        }
+        NetworkMode::Versus => {
+            let names = [name, player_names[0].clone()];
+            GameMode::Versus(Versus::network(connection, 0, names))
+        }
+    };
+    run_game(board.settings(), network_game, config);
+}
+
+/// Joins a game hosted by another player, and waits for it to start.
+fn join_network_game(config: &mut Config) {
+    let name = ask_name();
+    let address: String = dialoguer::Input::new()
+        .with_prompt("Please enter the address of the host")
+        .default(format!("127.0.0.1:{}", net::DEFAULT_PORT))
+        .interact()
+        .unwrap();
+    println!("Waiting for the host to start the game.");
+    match net::join(address.trim(), &name) {
+        Ok((board, player, connection)) => {
+            let network_game = match board.mode {
+                NetworkMode::Race => GameMode::Race(Race::join(connection, board.start_tile)),
+                NetworkMode::Coop => GameMode::Coop(Coop::join(connection, player, board.start_tile)),
+                NetworkMode::Versus => {
+                    GameMode::Versus(Versus::network(connection, player, [String::new(), name]))
+                }
+            };
+            run_game(board.settings(), network_game, config);
+        }
+        Err(e) => println!("Could not join the game: {}", e),
+    }
+}
+
+/// Asks for a game code until a valid one is entered, and plays the game it describes.
+fn play_code(config: &mut Config) {
+    let settings = loop {
+        let code: String = dialoguer::Input::new()
+            .with_prompt("Please enter the game code")
+            .interact()
+            .unwrap();
+        match GameSettings::from_code(&code) {
+            Ok(settings) => break settings,
+            Err(e) => println!("{}", e),
+        }
+    };
+    run_game(settings, GameMode::Classic, config);
+}
+
+/// Runs today's daily challenge of a chosen difficulty, and offers to export a summary of the result.
+///
+/// The challenges have the difficulties of the first square presets. Only the first game of each day is stored,
+/// later games of the same board can be played, but don't change the result.
+fn play_daily(config: &mut Config) {
+    let date = daily::today();
+    let results = daily::load_results();
+    let result_of = |difficulty: &str| {
+        results
+            .iter()
+            .find(|result| result.date == date && result.difficulty == difficulty)
+            .cloned()
+    };
+    let difficulties = &SQUARE_PRESETS[..3];
+    let names: Vec<String> = difficulties
+        .iter()
+        .map(|&(name, cols, rows, mines, _)| {
+            let played = match result_of(name) {
+                Some(result) => match result.outcome {
+                    DailyOutcome::Won => format!(" - cleared in {}", clock::format_duration(result.time)),
+                    DailyOutcome::HitAMine => " - hit a mine".to_string(),
+                    DailyOutcome::Forfeited => " - gave up".to_string(),
+                },
+                None => String::new(),
+            };
+            format!("{} [{}x{}] ({} mines){}", name, cols, rows, mines, played)
+        })
+        .collect();
+    let (name, cols, rows, mines, _) =
+        difficulties[select(&format!("Daily challenge of {}. Please select the difficulty.", date), &names)];
+
+    let played = result_of(name).is_some();
+    if played {
+        let again = dialoguer::Confirm::new()
+            .with_prompt("You have already played this board today. Play it again? Only your first result counts.")
+            .default(false)
+            .interact()
+            .unwrap_or(false);
+        if !again {
+            return export_daily_summary(result_of(name));
+        }
+    }
+
+    let daily = Daily::new(&date, name, cols, rows, !played);
+    let settings = GameSettings {
+        cols,
+        rows,
+        mines,
+        seed: Some(daily.seed()),
+        first_click_policy: FirstClickPolicy::Opening,
+        question_marks: false,
+        max_mines_per_tile: 1,
+        liar_region: None,
+        neighbourhood: Neighbourhood::Adjacent,
+        topology: Topology::Square,
+        mask: None,
+    };
+    run_game(settings, GameMode::Daily(daily), config);
+
+    let result = daily::load_results()
+        .into_iter()
+        .find(|result| result.date == date && result.difficulty == name);
+    export_daily_summary(result);
+}
+
+/// Shows the summary of a daily challenge, and offers to save it to a text file for sharing.
+fn export_daily_summary(result: Option<daily::DailyResult>) {
+    let summary = match result {
+        Some(result) => result.summary(),
+        None => return,
+    };
+    println!("\n{}", summary);
+    let export = dialoguer::Confirm::new()
+        .with_prompt("Do you want to save the summary to a file?")
+        .default(false)
+        .interact()
+        .unwrap_or(false);
+    if export {
+        let path: String = dialoguer::Input::new()
+            .with_prompt("Please enter the path of the file")
+            .default("rustsweeper-daily.txt".to_string())
+            .interact()
+            .unwrap();
+        if let Err(e) = std::fs::write(path.trim(), summary) {
+            println!("Could not save the summary: {}", e);
+        }
+    }
+}
+
+/// Sets up a versus game between two players sharing this computer, and runs it.
+fn play_versus(config: &mut Config) {
+    let names = [
+        ask_player_name("Player 1"),
+        ask_player_name("Player 2"),
+    ];
+    let preset_names: Vec<String> = SQUARE_PRESETS
+        .iter()
+        .map(|&(name, cols, rows, mines, _)| format!("{} [{}x{}] ({} mines)", name, cols, rows, mines))
+        .collect();
+    let (_, cols, rows, mines, _) =
+        SQUARE_PRESETS[select("Please select the board.", &preset_names)];
+    let settings = GameSettings {
+        cols,
+        rows,
+        mines,
+        seed: None,
+        first_click_policy: FirstClickPolicy::Unprotected,
+        question_marks: false,
+        max_mines_per_tile: 1,
+        liar_region: None,
+        neighbourhood: Neighbourhood::Adjacent,
+        topology: Topology::Square,
+        mask: None,
+    };
+    run_game(settings, GameMode::Versus(Versus::hot_seat(names)), config);
+}
+
+/// Watches the game played at the given address, without being able to affect it.
+fn spectate_game(address: &str, config: &mut Config) {
+    match spectate::spectate(address) {
+        Ok((settings, spectating)) => {
+            run_game(settings, GameMode::Spectate(spectating), config)
+        }
+        Err(e) => println!("Could not watch the game: {}", e),
+    }
+}
+
+/// Asks the user for the name to show to the other players.
+fn ask_name() -> String {
+    dialoguer::Input::new()
+        .with_prompt("Please enter your name")
+        .default(whoami())
+        .interact()
+        .unwrap()
+}
+
+/// Asks for the name of one of the players sharing this computer.
+fn ask_player_name(player: &str) -> String {
+    dialoguer::Input::new()
+        .with_prompt(format!("Please enter the name of {}", player))
+        .default(player.to_string())
+        .interact()
+        .unwrap()
+}
+
+fn whoami() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "Player".to_string())
+}
+
+/// Sets up an endless game and runs it.
+fn play_endless(config: &mut Config) {
+    let densities = [("Easy", 12), ("Normal", 16), ("Hard", 20), ("Insane", 25)];
+    let names: Vec<String> = densities
+        .iter()
+        .map(|(name, percentage)| format!("{} ({}% mines)", name, percentage))
+        .collect();
+    let (_, percentage) = densities[select("Please select the difficulty.", &names)];
+
+    let seed: String = dialoguer::Input::new()
+        .with_prompt("Please enter game seed (press Enter for random)")
+        .default(random_seed())
+        .show_default(false)
+        .interact()
+        .unwrap();
+
+    let (mut ctx, mut event_loop) =
+        build_context(30.0 * DEFAULT_TILE_SIZE, 20.0 * DEFAULT_TILE_SIZE + endless::STATUS_BAR_HEIGHT);
+    restore_window_position(&ctx, config);
+    let mut game = EndlessGame::new(&mut ctx, &seed, percentage as f64 / 100.0, load_theme(config));
+    game.keys = config.keys.clone();
+    game.mouse = config.mouse.clone();
+    game.question_marks = config.question_marks;
+    game.reveal_chords = config.reveal_chords;
+    game.flag_chords = config.flag_chords;
+    println!("Game initialized successfully. Scroll with the arrow keys or the mouse wheel.");
+
+    match event::run(&mut ctx, &mut event_loop, &mut game) {
+        Ok(_) => {
+            println!("Game exited cleanly.");
+        }
+        Err(e) => println!("Error occured: {}", e),
+    }
+    remember_window_position(&ctx, config);
+}
+
+/// The settings screen, where the settings that are kept between launches can be changed.
+/// Every change is saved right away.
+fn edit_settings(config: &mut Config) {
+    let on_off = |on: bool| if on { "on" } else { "off" };
+    loop {
+        // The theme is shown by the name in its manifest, which may differ from the name of its directory.
+        let theme_name = Theme::load(&config.theme).map_or_else(|_| config.theme.clone(), |theme| theme.name);
+        let mut items = vec![
+            format!("Preferred difficulty: {}", config.difficulty),
+            format!("Question marks: {}", on_off(config.question_marks)),
+            format!("Largest tile size: {} pixels", config.tile_size),
+            format!("Theme: {}", theme_name),
+            format!("Left mouse button {}", config.mouse.left),
+            format!("Right mouse button {}", config.mouse.right),
+            format!("Middle mouse button {}", config.mouse.middle),
+            format!(
+                "Revealing a revealed number chords it: {}",
+                on_off(config.reveal_chords)
+            ),
+            format!(
+                "Flagging a revealed number flags its mines: {}",
+                on_off(config.flag_chords)
+            ),
+        ];
+        let keys_start = items.len();
+        for (command, key) in config.keys.named() {
+            let key = if key.is_empty() { "none" } else { key.as_str() };
+            items.push(format!("Key that {}: {}", command, key));
+        }
+        let keys_end = items.len();
+        items.push("Forget the window position".to_string());
+        items.push("Back".to_string());
+
+        match select("Settings", &items) {
+            0 => {
+                let names: Vec<&str> = SQUARE_PRESETS.iter().map(|&(name, ..)| name).collect();
+                let choice = select("Please select the preferred difficulty.", &names);
+                config.difficulty = names[choice].to_string();
+            }
+            1 => config.question_marks = !config.question_marks,
+            2 => {
+                let prompt = "Please enter the largest tile size, in pixels";
+                config.tile_size = ask_number(prompt, config.tile_size as usize, 8..=200) as f32;
+            }
+            3 => {
+                let themes = theme::available_themes();
+                let name = &themes[select("Please select a theme.", &themes)];
+                // Broken themes are reported here rather than when a game starts.
+                match Theme::load(name) {
+                    Ok(_) => config.theme = name.clone(),
+                    Err(e) => println!("{}", e),
+                }
+            }
+            button @ 4..=6 => {
+                let command = Command::ALL[select("What should the button do?", &Command::ALL)];
+                match button {
+                    4 => config.mouse.left = command,
+                    5 => config.mouse.right = command,
+                    _ => config.mouse.middle = command,
+                }
+            }
+            7 => config.reveal_chords = !config.reveal_chords,
+            8 => config.flag_chords = !config.flag_chords,
+            binding if binding >= keys_start && binding < keys_end => {
+                let (command, key) = config.keys.named_mut().swap_remove(binding - keys_start);
+                *key = loop {
+                    let entered: String = dialoguer::Input::new()
+                        .with_prompt(format!(
+                            "Please enter the key that {}, or \"none\" for no key",
+                            command
+                        ))
+                        .default(if key.is_empty() { "none".to_string() } else { key.clone() })
+                        .interact()
+                        .unwrap();
+                    if entered.trim().eq_ignore_ascii_case("none") {
+                        break String::new();
+                    }
+                    match config::parse_key(&entered) {
+                        Some(parsed) => break format!("{:?}", parsed),
+                        None => println!("Unknown key. Keys are named like P, Key1, F5, Space or Escape."),
+                    }
+                };
+            }
+            window if window == keys_end => config.window_position = None,
+            _ => return,
+        }
+        save_config(config);
+    }
+}
+
+/// Creates the game window with the given size, with access to the resource directory.
+fn build_context(width: f32, height: f32) -> (Context, EventsLoop) {
     let mut cb = ggez::ContextBuilder::new("Rustsweeper", "Isak Einberg").window_mode(
         WindowMode::default()
-            .dimensions(
-                game_cols as f32 * scaled_tile_size,
-                game_rows as f32 * scaled_tile_size+15.0,
-            )
+            .dimensions(width, height)
             .resizable(false),
     );
 
@@ -186,25 +879,76 @@ fn main() {
         panic!("Could not find resource directory.")
     }
 
-    let (mut ctx, mut event_loop) = cb.build().unwrap();
+    let (ctx, event_loop) = cb.build().unwrap();
 
     // TODO! Set window icon
-    graphics::set_window_title(&mut ctx, "Rustsweeper");
-
-    let mut game = GameContainer::new(
-        &mut ctx,
-        game_rows,
-        game_cols,
-        game_mines,
-        game_seed,
-        scaled_tile_size,
-    );
-    println!("Game initialized successfully.");
+    graphics::set_window_title(&ctx, "Rustsweeper");
 
-    match event::run(&mut ctx, &mut event_loop, &mut game) {
-        Ok(_) => {
-            println!("Game exited cleanly.");
+    (ctx, event_loop)
+}
+
+/// A random seed of 20 letters and digits, for games the player hasn't given a seed.
+fn random_seed() -> String {
+    rand::thread_rng()
+        .sample_iter(Alphanumeric)
+        .take(20)
+        .map(char::from)
+        .collect()
+}
+
+/// Asks the user for the amount of mines on a board with the given amount of tiles.
+fn ask_mines(tiles: usize) -> usize {
+    loop {
+        let mines: usize = dialoguer::Input::new()
+            .with_prompt("Please enter the amount of mines")
+            .default(tiles * 15 / 100)
+            .interact()
+            .unwrap();
+        if mines < tiles {
+            return mines;
+        }
+        println!("There must be fewer mines than the {} tiles of the board.", tiles);
+    }
+}
+
+/// Asks the user for a number in the given range.
+fn ask_number(prompt: &str, default: usize, range: std::ops::RangeInclusive<usize>) -> usize {
+    loop {
+        let number: usize = dialoguer::Input::new()
+            .with_prompt(prompt)
+            .default(default)
+            .interact()
+            .unwrap();
+        if range.contains(&number) {
+            return number;
+        }
+        println!("Please enter a number from {} to {}.", range.start(), range.end());
+    }
+}
+
+/// Lets the user pick one of the given options, and returns the index of the chosen option.
+///
+/// Exits the game if the user presses Q.
+fn select<T: ToString>(prompt: &str, items: &[T]) -> usize {
+    select_with_default(prompt, items, 0)
+}
+
+/// Like `select`, with the option at the given index selected to begin with.
+fn select_with_default<T: ToString>(prompt: &str, items: &[T], default: usize) -> usize {
+    loop {
+        match dialoguer::Select::with_theme(&dialoguer::theme::ColorfulTheme::default())
+            .items(items)
+            .default(default)
+            .with_prompt(prompt)
+            .interact_opt()
+        {
+            Ok(Some(alt)) => return alt,
+            Ok(None) => exit(0),
+            Err(_) => {
+                println!(
+                    "You need to choose one of the options. Please try again or press Q to exit."
+                );
+            }
         }
-        Err(e) => println!("Error occured: {}", e),
     }
 }
@@ -0,0 +1,192 @@
+use std::{fmt, fs, path::Path};
+
+use crate::game::MAX_BOARD_SIDE;
+
+/// The shape of a board, as a grid of cells that are either part of the board (active) or holes in it.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Mask {
+    pub(crate) cols: usize,
+    pub(crate) rows: usize,
+    /// Row-major, like `Board`. `true` for cells that are part of the board.
+    pub(crate) active: Vec<bool>,
+}
+
+impl Mask {
+    /// Loads a mask from an image or from a text file, depending on the file extension.
+    ///
+    /// In images, dark and opaque pixels are part of the board. In text files, each line is a row of the board,
+    /// where `.` and spaces are holes and any other character is part of the board.
+    /// Masks can be at most `MAX_BOARD_SIDE` cells across and down, like any other board.
+    pub(crate) fn load(path: &Path) -> Result<Mask, MaskError> {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+        let mask = match extension.as_deref() {
+            Some("png") | Some("bmp") => Mask::from_image(path)?,
+            _ => Mask::from_text(&fs::read_to_string(path).map_err(MaskError::Io)?)?,
+        };
+
+        if mask.active_cells() == 0 {
+            return Err(MaskError::Empty);
+        }
+        Ok(mask)
+    }
+
+    fn from_text(text: &str) -> Result<Mask, MaskError> {
+        let lines: Vec<&str> = text.lines().collect();
+        let cols = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        let rows = lines.len();
+        check_size(cols, rows)?;
+
+        let mut active = vec![false; cols * rows];
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                active[y * cols + x] = c != '.' && !c.is_whitespace();
+            }
+        }
+        Ok(Mask { cols, rows, active })
+    }
+
+    fn from_image(path: &Path) -> Result<Mask, MaskError> {
+        // Checked before decoding, so that a huge image isn't loaded only to be rejected.
+        let (cols, rows) = image::image_dimensions(path).map_err(MaskError::Image)?;
+        check_size(cols as usize, rows as usize)?;
+        let image = image::open(path).map_err(MaskError::Image)?.to_rgba();
+        let (cols, rows) = (image.width() as usize, image.height() as usize);
+        let active = image
+            .pixels()
+            .map(|pixel| {
+                let [r, g, b, a] = pixel.0;
+                let luminance = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+                a >= 128 && luminance < 128
+            })
+            .collect();
+        Ok(Mask { cols, rows, active })
+    }
+
+    pub(crate) fn active_cells(&self) -> usize {
+        self.active.iter().filter(|&&active| active).count()
+    }
+}
+
+fn check_size(cols: usize, rows: usize) -> Result<(), MaskError> {
+    if cols > MAX_BOARD_SIDE || rows > MAX_BOARD_SIDE {
+        return Err(MaskError::TooLarge { cols, rows });
+    }
+    Ok(())
+}
+
+/// Errors that can occur when loading a mask.
+#[derive(Debug)]
+pub(crate) enum MaskError {
+    Io(std::io::Error),
+    Image(image::ImageError),
+    Empty,
+    TooLarge { cols: usize, rows: usize },
+}
+
+impl fmt::Display for MaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaskError::Io(e) => write!(f, "Could not read the mask file: {}", e),
+            MaskError::Image(e) => write!(f, "Could not read the mask image: {}", e),
+            MaskError::Empty => write!(f, "Invalid mask: it doesn't contain any tiles"),
+            MaskError::TooLarge { cols, rows } => write!(
+                f,
+                "Invalid mask: it is {}x{} tiles, but boards can be at most {}x{} tiles",
+                cols, rows, MAX_BOARD_SIDE, MAX_BOARD_SIDE
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MaskError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes the mask to a temporary file with the given extension, and loads it from there.
+    fn load(name: &str, write: impl FnOnce(&Path)) -> Result<Mask, MaskError> {
+        let path = std::env::temp_dir().join(format!("rustsweeper-mask-{}", name));
+        write(&path);
+        let result = Mask::load(&path);
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    fn load_text(name: &str, text: &str) -> Result<Mask, MaskError> {
+        load(name, |path| fs::write(path, text).unwrap())
+    }
+
+    fn load_image(
+        name: &str,
+        (cols, rows): (u32, u32),
+        dark: &[(u32, u32)],
+    ) -> Result<Mask, MaskError> {
+        let image = image::RgbaImage::from_fn(cols, rows, |x, y| {
+            if dark.contains(&(x, y)) {
+                image::Rgba([0, 0, 0, 255])
+            } else {
+                image::Rgba([255, 255, 255, 255])
+            }
+        });
+        load(name, |path| image.save(path).unwrap())
+    }
+
+    #[test]
+    fn text_masks_are_padded_to_the_longest_line() {
+        let mask = load_text("ragged.txt", "#.#\n# ##\nx\n").unwrap();
+        assert_eq!((mask.cols, mask.rows), (4, 3));
+        let active: Vec<bool> = "#.#.# ##x..."
+            .chars()
+            .map(|c| c != '.' && c != ' ')
+            .collect();
+        assert_eq!(mask.active, active);
+        assert_eq!(mask.active_cells(), 6);
+    }
+
+    #[test]
+    fn image_masks_are_made_of_the_dark_pixels() {
+        let mask = load_image("pixels.png", (3, 2), &[(0, 0), (2, 1)]).unwrap();
+        assert_eq!((mask.cols, mask.rows), (3, 2));
+        assert_eq!(mask.active, vec![true, false, false, false, false, true]);
+    }
+
+    #[test]
+    fn masks_without_tiles_are_rejected() {
+        assert!(matches!(
+            load_text("holes.txt", "..\n  \n"),
+            Err(MaskError::Empty)
+        ));
+        assert!(matches!(
+            load_image("white.png", (2, 2), &[]),
+            Err(MaskError::Empty)
+        ));
+    }
+
+    #[test]
+    fn masks_larger_than_a_board_are_rejected() {
+        let wide = "#".repeat(MAX_BOARD_SIDE + 1);
+        assert!(matches!(
+            load_text("wide.txt", &wide),
+            Err(MaskError::TooLarge { cols, rows: 1 }) if cols == MAX_BOARD_SIDE + 1
+        ));
+        let tall = "#\n".repeat(MAX_BOARD_SIDE + 1);
+        assert!(matches!(
+            load_text("tall.txt", &tall),
+            Err(MaskError::TooLarge { cols: 1, .. })
+        ));
+        let side = MAX_BOARD_SIDE as u32 + 1;
+        assert!(matches!(
+            load_image("large.png", (side, 2), &[(0, 0)]),
+            Err(MaskError::TooLarge { .. })
+        ));
+        assert!(load_text("largest.txt", &"#".repeat(MAX_BOARD_SIDE)).is_ok());
+    }
+}
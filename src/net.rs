@@ -0,0 +1,739 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    board::Tile,
+    game::{Action, FirstClickPolicy, GameSettings, GameState},
+    topology::{Neighbourhood, Topology},
+};
+
+/// The port games are hosted on, unless another one is chosen.
+pub(crate) const DEFAULT_PORT: u16 = 7878;
+/// The most players a game can have, including the host. Messages about other players are dropped.
+const MAX_PLAYERS: usize = 64;
+/// How long a new connection has to introduce itself before the lobby gives up on it.
+const HELLO_TIMEOUT: Duration = Duration::from_secs(10);
+/// How often the lobby checks for new connections.
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How the players of a networked game play together.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum NetworkMode {
+    /// Every player plays their own copy of the board, and the first to clear it wins.
+    Race,
+    /// All players play on the same board, which is kept by the host.
+    Coop,
+    /// Two players take turns on the same board, competing to find the most mines.
+    Versus,
+}
+
+impl std::fmt::Display for NetworkMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            NetworkMode::Race => "Race on identical boards",
+            NetworkMode::Coop => "Co-op on a shared board",
+            NetworkMode::Versus => "Versus: take turns finding the most mines (2 players)",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// The board of a networked game, which every player gets an identical copy of.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct SharedBoard {
+    pub(crate) mode: NetworkMode,
+    pub(crate) cols: usize,
+    pub(crate) rows: usize,
+    pub(crate) mines: usize,
+    /// The tile every player starts by revealing. Mines are distributed around it, so it decides the board
+    /// as much as the seed does.
+    pub(crate) start_tile: usize,
+    pub(crate) seed: String,
+}
+
+impl SharedBoard {
+    /// The settings of a game on the board. Networked games are always played on regular boards with classic rules,
+    /// starting with an opening on the common start tile.
+    /// In a versus game, where finding mines is the point, the first tile isn't protected.
+    pub(crate) fn settings(&self) -> GameSettings {
+        GameSettings {
+            cols: self.cols,
+            rows: self.rows,
+            mines: self.mines,
+            seed: Some(self.seed.clone()),
+            first_click_policy: match self.mode {
+                NetworkMode::Versus => FirstClickPolicy::Unprotected,
+                _ => FirstClickPolicy::Opening,
+            },
+            question_marks: false,
+            max_mines_per_tile: 1,
+            liar_region: None,
+            neighbourhood: Neighbourhood::Adjacent,
+            topology: Topology::Square,
+            mask: None,
+        }
+    }
+}
+
+/// A message of the network protocol, sent as a single line of text.
+///
+/// Free text such as names and seeds always comes last on the line, so that it may contain spaces.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Message {
+    /// Sent by a player joining a game: `HELLO <name>`.
+    Hello { name: String },
+    /// Sent by the host when the game starts: `START <mode> <cols> <rows> <mines> <start tile> <seed>`.
+    Start(SharedBoard),
+    /// The settings of a watched game, sent to a spectator when it connects:
+    /// `GAME <topology> <layers> <cols> <rows> <mines> <first click policy> <question marks> <mines per tile>
+    /// <liar region or -> <neighbourhood> <seed>`. Only games without a mask, on regular boards, can be described.
+    Game(GameSettings),
+    /// Sent by the host to each player when the game starts, with the number of that player: `WELCOME <player>`.
+    Welcome { player: usize },
+    /// The name of a player, sent by the host: `PLAYER <player> <name>`.
+    Player { player: usize, name: String },
+    /// An action of a player in a co-op game: `ACTION <player> <reveal|flag|chord> <tile>`.
+    /// Sent by players to the host, which decides on the outcome.
+    Action { player: usize, action: Action },
+    /// The position of a player's mouse, in tiles from the top-left corner of the board: `CURSOR <player> <x> <y>`.
+    Cursor { player: usize, x: f32, y: f32 },
+    /// Tiles of a co-op game that have changed, as seen by the players: `TILES <tile>:<bits> <tile>:<bits> ...`.
+    Tiles(Vec<(usize, Tile)>),
+    /// The state of a co-op game: `STATE <state> <flags placed> <elapsed time in ms>`.
+    State {
+        state: GameState,
+        flags: isize,
+        elapsed_ms: u64,
+    },
+    /// The progress of a player: `PROGRESS <player> <percent cleared> <state> <time in ms or -> <name>`.
+    /// Sent by players about themselves, and by the host about every player.
+    Progress {
+        player: usize,
+        percent: u8,
+        state: GameState,
+        time_ms: Option<u64>,
+        name: String,
+    },
+}
+
+impl Message {
+    /// Parses a line of the protocol. Returns `None` for lines that aren't valid messages, including games that
+    /// couldn't be started, like a game code describing them couldn't, and players beyond `MAX_PLAYERS`.
+    pub(crate) fn parse(line: &str) -> Option<Message> {
+        let line = line.trim_end_matches(&['\r', '\n'][..]);
+        let (kind, rest) = split_word(line);
+        match kind {
+            "HELLO" => Some(Message::Hello {
+                name: rest.to_string(),
+            }),
+            "START" => {
+                let (mode, rest) = split_word(rest);
+                let (cols, rest) = split_word(rest);
+                let (rows, rest) = split_word(rest);
+                let (mines, rest) = split_word(rest);
+                let (start_tile, seed) = split_word(rest);
+                let board = SharedBoard {
+                    mode: match mode {
+                        "race" => NetworkMode::Race,
+                        "coop" => NetworkMode::Coop,
+                        "versus" => NetworkMode::Versus,
+                        _ => return None,
+                    },
+                    cols: cols.parse().ok()?,
+                    rows: rows.parse().ok()?,
+                    mines: mines.parse().ok()?,
+                    start_tile: start_tile.parse().ok()?,
+                    seed: seed.to_string(),
+                };
+                board.settings().validate(Some(board.start_tile)).ok()?;
+                Some(Message::Start(board))
+            }
+            "GAME" => {
+                let (topology, rest) = split_word(rest);
+                let (layers, rest) = split_word(rest);
+                let (cols, rest) = split_word(rest);
+                let (rows, rest) = split_word(rest);
+                let (mines, rest) = split_word(rest);
+                let (first_click_policy, rest) = split_word(rest);
+                let (question_marks, rest) = split_word(rest);
+                let (max_mines_per_tile, rest) = split_word(rest);
+                let (liar_region, rest) = split_word(rest);
+                let (neighbourhood, seed) = split_word(rest);
+                let settings = GameSettings {
+                    cols: cols.parse().ok()?,
+                    rows: rows.parse().ok()?,
+                    mines: mines.parse().ok()?,
+                    seed: Some(seed.to_string()),
+                    first_click_policy: match first_click_policy {
+                        "unprotected" => FirstClickPolicy::Unprotected,
+                        "safe-tile" => FirstClickPolicy::SafeTile,
+                        "opening" => FirstClickPolicy::Opening,
+                        "safe-area" => FirstClickPolicy::SafeArea,
+                        _ => return None,
+                    },
+                    question_marks: question_marks == "1",
+                    max_mines_per_tile: max_mines_per_tile.parse().ok()?,
+                    liar_region: match liar_region {
+                        "-" => None,
+                        liar_region => Some(liar_region.parse().ok()?),
+                    },
+                    neighbourhood: match neighbourhood {
+                        "adjacent" => Neighbourhood::Adjacent,
+                        "knight" => Neighbourhood::Knight,
+                        "radius2" => Neighbourhood::Radius2,
+                        _ => return None,
+                    },
+                    topology: match topology {
+                        "square" => Topology::Square,
+                        "torus" => Topology::Torus,
+                        "hex" => Topology::Hex,
+                        "triangle" => Topology::Triangle,
+                        "cube" => Topology::Cube {
+                            layers: layers.parse().ok()?,
+                        },
+                        _ => return None,
+                    },
+                    mask: None,
+                };
+                settings.validate(None).ok()?;
+                Some(Message::Game(settings))
+            }
+            "PROGRESS" => {
+                let (player, rest) = split_word(rest);
+                let (percent, rest) = split_word(rest);
+                let (state, rest) = split_word(rest);
+                let (time_ms, name) = split_word(rest);
+                Some(Message::Progress {
+                    player: parse_player(player)?,
+                    // Progress beyond 100% can't happen, and would break the ranking of the players.
+                    percent: percent.parse().ok().filter(|&percent| percent <= 100)?,
+                    state: parse_state(state)?,
+                    time_ms: match time_ms {
+                        "-" => None,
+                        time_ms => Some(time_ms.parse().ok()?),
+                    },
+                    name: name.to_string(),
+                })
+            }
+            "WELCOME" => Some(Message::Welcome {
+                player: parse_player(rest)?,
+            }),
+            "PLAYER" => {
+                let (player, name) = split_word(rest);
+                Some(Message::Player {
+                    player: parse_player(player)?,
+                    name: name.to_string(),
+                })
+            }
+            "ACTION" => {
+                let (player, rest) = split_word(rest);
+                let (kind, tile) = split_word(rest);
+                let tile = tile.parse().ok()?;
+                Some(Message::Action {
+                    player: parse_player(player)?,
+                    action: match kind {
+                        "reveal" => Action::Reveal(tile),
+                        "flag" => Action::Flag(tile),
+                        "chord" => Action::Chord(tile),
+                        _ => return None,
+                    },
+                })
+            }
+            "CURSOR" => {
+                let (player, rest) = split_word(rest);
+                let (x, y) = split_word(rest);
+                Some(Message::Cursor {
+                    player: parse_player(player)?,
+                    x: x.parse().ok()?,
+                    y: y.parse().ok()?,
+                })
+            }
+            "TILES" => rest
+                .split_whitespace()
+                .map(|change| {
+                    let (tile, bits) = change.split_at(change.find(':')?);
+                    Some((tile.parse().ok()?, Tile::from_bits(bits[1..].parse().ok()?)))
+                })
+                .collect::<Option<_>>()
+                .map(Message::Tiles),
+            "STATE" => {
+                let (state, rest) = split_word(rest);
+                let (flags, elapsed_ms) = split_word(rest);
+                Some(Message::State {
+                    state: parse_state(state)?,
+                    flags: flags.parse().ok()?,
+                    elapsed_ms: elapsed_ms.parse().ok()?,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Formats the message as a line of the protocol, including the line break.
+    pub(crate) fn to_line(&self) -> String {
+        match self {
+            Message::Hello { name } => format!("HELLO {}\n", name),
+            Message::Start(board) => format!(
+                "START {} {} {} {} {} {}\n",
+                match board.mode {
+                    NetworkMode::Race => "race",
+                    NetworkMode::Coop => "coop",
+                    NetworkMode::Versus => "versus",
+                },
+                board.cols,
+                board.rows,
+                board.mines,
+                board.start_tile,
+                board.seed
+            ),
+            Message::Game(settings) => format!(
+                "GAME {} {} {} {} {} {} {} {} {} {} {}\n",
+                match settings.topology {
+                    Topology::Square => "square",
+                    Topology::Torus => "torus",
+                    Topology::Hex => "hex",
+                    Topology::Triangle => "triangle",
+                    Topology::Cube { .. } => "cube",
+                    Topology::Graph { .. } => "graph",
+                },
+                match settings.topology {
+                    Topology::Cube { layers } => layers,
+                    _ => 1,
+                },
+                settings.cols,
+                settings.rows,
+                settings.mines,
+                match settings.first_click_policy {
+                    FirstClickPolicy::Unprotected => "unprotected",
+                    FirstClickPolicy::SafeTile => "safe-tile",
+                    FirstClickPolicy::Opening => "opening",
+                    FirstClickPolicy::SafeArea => "safe-area",
+                },
+                settings.question_marks as u8,
+                settings.max_mines_per_tile,
+                settings
+                    .liar_region
+                    .map_or("-".to_string(), |region| region.to_string()),
+                match settings.neighbourhood {
+                    Neighbourhood::Adjacent => "adjacent",
+                    Neighbourhood::Knight => "knight",
+                    Neighbourhood::Radius2 => "radius2",
+                },
+                settings.seed.as_deref().unwrap_or_default()
+            ),
+            Message::Welcome { player } => format!("WELCOME {}\n", player),
+            Message::Player { player, name } => format!("PLAYER {} {}\n", player, name),
+            Message::Action { player, action } => {
+                let (kind, tile) = match action {
+                    Action::Reveal(tile) => ("reveal", tile),
+                    Action::Flag(tile) => ("flag", tile),
+                    Action::Chord(tile) => ("chord", tile),
+                };
+                format!("ACTION {} {} {}\n", player, kind, tile)
+            }
+            Message::Cursor { player, x, y } => format!("CURSOR {} {} {}\n", player, x, y),
+            Message::Tiles(tiles) => {
+                let mut line = "TILES".to_string();
+                for (tile, bits) in tiles {
+                    line.push_str(&format!(" {}:{}", tile, bits.bits()));
+                }
+                line + "\n"
+            }
+            Message::State {
+                state,
+                flags,
+                elapsed_ms,
+            } => format!("STATE {} {} {}\n", format_state(*state), flags, elapsed_ms),
+            Message::Progress {
+                player,
+                percent,
+                state,
+                time_ms,
+                name,
+            } => format!(
+                "PROGRESS {} {} {} {} {}\n",
+                player,
+                percent,
+                format_state(*state),
+                time_ms.map_or("-".to_string(), |time_ms| time_ms.to_string()),
+                name
+            ),
+        }
+    }
+}
+
+/// Splits off the first word of the line, and returns it together with the rest of the line.
+fn split_word(line: &str) -> (&str, &str) {
+    match line.find(' ') {
+        Some(space) => (&line[..space], &line[space + 1..]),
+        None => (line, ""),
+    }
+}
+
+fn parse_player(player: &str) -> Option<usize> {
+    player.parse().ok().filter(|&player| player < MAX_PLAYERS)
+}
+
+fn format_state(state: GameState) -> &'static str {
+    match state {
+        GameState::Playing => "playing",
+        GameState::Won => "won",
+        GameState::Lost => "lost",
+    }
+}
+
+fn parse_state(state: &str) -> Option<GameState> {
+    match state {
+        "playing" => Some(GameState::Playing),
+        "won" => Some(GameState::Won),
+        "lost" => Some(GameState::Lost),
+        _ => None,
+    }
+}
+
+/// The open connections of a game: to every player for the host, or to the host for a player.
+///
+/// Each connection is read by a thread of its own, which hands the messages over through a channel,
+/// so that the game can check for new messages without blocking.
+pub(crate) struct Connection {
+    peers: Vec<TcpStream>,
+    incoming: Receiver<(usize, Message)>,
+}
+
+impl Connection {
+    /// Takes over the given connections, each with the reader that has been used for it so far.
+    pub(crate) fn new(peers: Vec<(TcpStream, BufReader<TcpStream>)>) -> Connection {
+        let (sender, incoming) = mpsc::channel();
+        let peers = peers
+            .into_iter()
+            .enumerate()
+            .map(|(peer, (stream, reader))| {
+                let sender = sender.clone();
+                thread::spawn(move || read_messages(peer, reader, sender));
+                stream
+            })
+            .collect();
+        Connection { peers, incoming }
+    }
+
+    /// Sends a message to a single peer.
+    ///
+    /// A peer that has disconnected is not an error, it simply stops receiving messages.
+    pub(crate) fn send(&mut self, peer: usize, message: &Message) {
+        let _ = self.peers[peer].write_all(message.to_line().as_bytes());
+    }
+
+    /// Sends a message to every peer.
+    pub(crate) fn broadcast(&mut self, message: &Message) {
+        for peer in 0..self.peers.len() {
+            self.send(peer, message);
+        }
+    }
+
+    /// The messages that have arrived since the last call, together with the peer that sent them.
+    pub(crate) fn receive(&self) -> Vec<(usize, Message)> {
+        self.incoming.try_iter().collect()
+    }
+}
+
+fn read_messages(peer: usize, reader: BufReader<TcpStream>, sender: Sender<(usize, Message)>) {
+    for line in reader.lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        if let Some(message) = Message::parse(&line) {
+            if sender.send((peer, message)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// A player who has joined a hosted game, but whose game hasn't started yet.
+type WaitingPlayer = (String, TcpStream, BufReader<TcpStream>);
+
+/// A hosted game that players can join until it is started.
+pub(crate) struct Lobby {
+    players: Arc<Mutex<Vec<WaitingPlayer>>>,
+    /// Set once the game has started, which closes the port and turns away players who are still joining.
+    /// Only changed while `players` is locked.
+    started: Arc<AtomicBool>,
+}
+
+impl Lobby {
+    /// Starts accepting players on the given port, on all network interfaces.
+    ///
+    /// Each new connection introduces itself on a thread of its own, so that a connection that never does
+    /// doesn't keep anyone else from joining.
+    pub(crate) fn host(port: u16) -> io::Result<Lobby> {
+        Lobby::open(TcpListener::bind(("0.0.0.0", port))?)
+    }
+
+    fn open(listener: TcpListener) -> io::Result<Lobby> {
+        // Polled, so that the listener can be dropped once the game starts.
+        listener.set_nonblocking(true)?;
+        let lobby = Lobby {
+            players: Arc::new(Mutex::new(Vec::new())),
+            started: Arc::new(AtomicBool::new(false)),
+        };
+        let (players, started) = (Arc::clone(&lobby.players), Arc::clone(&lobby.started));
+        thread::spawn(move || {
+            while !started.load(Ordering::SeqCst) {
+                match listener.accept() {
+                    Ok((stream, _)) => {
+                        let (players, started) = (Arc::clone(&players), Arc::clone(&started));
+                        thread::spawn(move || greet(stream, &players, &started));
+                    }
+                    Err(_) => thread::sleep(ACCEPT_INTERVAL),
+                }
+            }
+        });
+        Ok(lobby)
+    }
+
+    /// Stops letting players in, and returns the names of the players who joined and the connection to them.
+    pub(crate) fn start(self) -> (Vec<String>, Connection) {
+        let players = {
+            let mut players = self.players.lock().unwrap();
+            self.started.store(true, Ordering::SeqCst);
+            std::mem::take(&mut *players)
+        };
+        let (names, peers) = players
+            .into_iter()
+            .map(|(name, stream, reader)| (name, (stream, reader)))
+            .unzip();
+        (names, Connection::new(peers))
+    }
+}
+
+/// Waits for a new connection to introduce itself, and lets it join the lobby if the game hasn't started yet.
+/// A connection only counts as a player once it has.
+fn greet(stream: TcpStream, players: &Mutex<Vec<WaitingPlayer>>, started: &AtomicBool) {
+    let hello = || -> io::Result<(String, BufReader<TcpStream>)> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(HELLO_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        stream.set_read_timeout(None)?;
+        match Message::parse(&line) {
+            Some(Message::Hello { name }) => Ok((name, reader)),
+            _ => Err(io::ErrorKind::InvalidData.into()),
+        }
+    };
+    let (name, reader) = match hello() {
+        Ok(hello) => hello,
+        Err(_) => return,
+    };
+    let mut players = players.lock().unwrap();
+    // The host is a player as well.
+    if !started.load(Ordering::SeqCst) && players.len() + 1 < MAX_PLAYERS {
+        println!("{} joined the game.", name);
+        players.push((name, stream, reader));
+    }
+}
+
+/// Joins the game hosted at the given address, and waits for the host to start it.
+///
+/// Returns the board the host started the game with, the number of this player and the connection to the host.
+pub(crate) fn join(address: &str, name: &str) -> io::Result<(SharedBoard, usize, Connection)> {
+    let address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid address"))?;
+    let mut stream = TcpStream::connect_timeout(&address, Duration::from_secs(5))?;
+    stream.write_all(
+        Message::Hello {
+            name: name.to_string(),
+        }
+        .to_line()
+        .as_bytes(),
+    )?;
+
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut board = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "The host closed the connection",
+            ));
+        }
+        match (Message::parse(&line), board.take()) {
+            (Some(Message::Start(start)), _) => board = Some(start),
+            // The host welcomes each player right after starting the game.
+            (Some(Message::Welcome { player }), Some(board)) => {
+                return Ok((board, player, Connection::new(vec![(stream, reader)])));
+            }
+            (_, previous) => board = previous,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board(start_tile: usize) -> SharedBoard {
+        SharedBoard {
+            mode: NetworkMode::Coop,
+            cols: 9,
+            rows: 9,
+            mines: 10,
+            start_tile,
+            seed: "a seed with spaces".to_string(),
+        }
+    }
+
+    #[test]
+    fn every_message_is_parsed_back() {
+        let mut revealed = Tile::default();
+        revealed.set_revealed(true);
+        revealed.set_number(Some(3));
+        let messages = vec![
+            Message::Hello {
+                name: "Alice Smith".to_string(),
+            },
+            Message::Start(board(40)),
+            Message::Start(SharedBoard {
+                mode: NetworkMode::Versus,
+                ..board(0)
+            }),
+            Message::Game(GameSettings {
+                topology: Topology::Cube { layers: 3 },
+                first_click_policy: FirstClickPolicy::SafeArea,
+                question_marks: true,
+                max_mines_per_tile: 2,
+                liar_region: Some(4),
+                neighbourhood: Neighbourhood::Knight,
+                ..board(0).settings()
+            }),
+            Message::Game(board(0).settings()),
+            Message::Welcome { player: 2 },
+            Message::Player {
+                player: 1,
+                name: "Bob".to_string(),
+            },
+            Message::Action {
+                player: 1,
+                action: Action::Reveal(5),
+            },
+            Message::Action {
+                player: 2,
+                action: Action::Flag(80),
+            },
+            Message::Action {
+                player: 3,
+                action: Action::Chord(0),
+            },
+            Message::Cursor {
+                player: 1,
+                x: 2.75,
+                y: 0.1,
+            },
+            Message::Tiles(vec![(0, Tile::default()), (17, revealed)]),
+            Message::Tiles(Vec::new()),
+            Message::State {
+                state: GameState::Lost,
+                flags: -2,
+                elapsed_ms: 12_345,
+            },
+            Message::Progress {
+                player: 0,
+                percent: 100,
+                state: GameState::Won,
+                time_ms: Some(61_000),
+                name: "Carol".to_string(),
+            },
+            Message::Progress {
+                player: 4,
+                percent: 0,
+                state: GameState::Playing,
+                time_ms: None,
+                name: String::new(),
+            },
+        ];
+        for message in messages {
+            assert_eq!(Message::parse(&message.to_line()), Some(message));
+        }
+    }
+
+    #[test]
+    fn truncated_lines_are_rejected() {
+        for line in &[
+            "",
+            "START race 9 9 10",
+            "GAME square 1 9 9 10 opening 0",
+            "WELCOME",
+            "PLAYER",
+            "ACTION 1 reveal",
+            "CURSOR 1 2.5",
+            "TILES 3",
+            "STATE won 3",
+            "PROGRESS 1 50",
+        ] {
+            assert_eq!(Message::parse(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn fields_out_of_range_are_rejected() {
+        for line in &[
+            "START tetris 9 9 10 40 seed",
+            // Off the board, and a board without tiles.
+            "START race 9 9 10 81 seed",
+            "START race 0 9 10 0 seed",
+            // More mines than fit around the opening.
+            "START race 9 9 73 40 seed",
+            "START race 9 9 0 40 seed",
+            "ACTION 1 dig 5",
+            "ACTION 64 reveal 5",
+            "WELCOME 64",
+            "TILES 3:65536",
+            "STATE paused 0 0",
+            "GAME square 1 9 9 10 opening 0 4 - adjacent seed",
+            "GAME square 1 9 9 10 opening 0 1 7 adjacent seed",
+            "GAME cube 0 9 9 10 opening 0 1 - adjacent seed",
+            "GAME square 1 2000 2000 10 opening 0 1 - adjacent seed",
+            "PROGRESS 1 101 playing - Alice",
+            "PROGRESS 1 -1 playing - Alice",
+        ] {
+            assert_eq!(Message::parse(line), None, "{:?}", line);
+        }
+        assert!(Message::parse("START race 9 9 72 40 seed").is_some());
+        assert!(Message::parse("ACTION 63 reveal 5").is_some());
+    }
+
+    #[test]
+    fn a_silent_connection_does_not_hold_up_the_lobby() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let lobby = Lobby::open(listener).unwrap();
+
+        let _silent = TcpStream::connect(address).unwrap();
+        let mut player = TcpStream::connect(address).unwrap();
+        player.write_all(b"HELLO Alice\n").unwrap();
+        for _ in 0..100 {
+            if !lobby.players.lock().unwrap().is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+
+        let (names, _connection) = lobby.start();
+        assert_eq!(names, vec!["Alice".to_string()]);
+        thread::sleep(ACCEPT_INTERVAL * 4);
+        assert!(TcpStream::connect(address).is_err());
+    }
+}
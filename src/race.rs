@@ -0,0 +1,191 @@
+use std::time::Duration;
+
+use crate::{
+    board::MineDistributionError,
+    game::{Action, GameContainer, GameMode, GameState},
+    net::{Connection, Message},
+};
+
+/// The width of the sidebar next to the board of a race, in pixels.
+pub(crate) const SIDEBAR_WIDTH: f32 = 240.0;
+
+/// A player of a race, as shown in the sidebar.
+#[derive(Clone, Debug)]
+pub(crate) struct RacePlayer {
+    pub(crate) name: String,
+    /// The percentage of the safe tiles the player has revealed.
+    pub(crate) percent: u8,
+    pub(crate) state: GameState,
+    /// The time it took the player to win or lose, `None` while the player is still playing.
+    pub(crate) time: Option<Duration>,
+}
+
+impl RacePlayer {
+    fn new(name: String) -> RacePlayer {
+        RacePlayer {
+            name,
+            percent: 0,
+            state: GameState::Playing,
+            time: None,
+        }
+    }
+
+    fn to_message(&self, player: usize) -> Message {
+        Message::Progress {
+            player,
+            percent: self.percent,
+            state: self.state,
+            time_ms: self.time.map(|time| time.as_millis() as u64),
+            name: self.name.clone(),
+        }
+    }
+}
+
+/// A race between players on identical boards, over the network.
+///
+/// The host keeps track of the progress of every player and broadcasts it, the other players only report their own.
+pub(crate) struct Race {
+    connection: Connection,
+    is_host: bool,
+    /// The players, numbered in the order they joined. The host is player 0.
+    /// A joined player learns about the players from the host, so the list is empty until the first update.
+    players: Vec<RacePlayer>,
+    /// The tile every player starts by revealing, so that everyone gets the same board.
+    pub(crate) start_tile: usize,
+}
+
+impl Race {
+    /// A race hosted by this player, against the players who joined the lobby.
+    pub(crate) fn host(
+        connection: Connection,
+        host_name: String,
+        player_names: Vec<String>,
+        start_tile: usize,
+    ) -> Race {
+        let players = std::iter::once(host_name)
+            .chain(player_names)
+            .map(RacePlayer::new)
+            .collect();
+        Race {
+            connection,
+            is_host: true,
+            players,
+            start_tile,
+        }
+    }
+
+    /// A race hosted by another player.
+    pub(crate) fn join(connection: Connection, start_tile: usize) -> Race {
+        Race {
+            connection,
+            is_host: false,
+            players: Vec::new(),
+            start_tile,
+        }
+    }
+
+    /// The players ranked by how well they are doing: finished players by their time, then players still playing
+    /// by how much they have cleared, then players who hit a mine.
+    pub(crate) fn ranking(&self) -> Vec<&RacePlayer> {
+        let mut ranking: Vec<&RacePlayer> = self.players.iter().collect();
+        ranking.sort_by_key(|player| match player.state {
+            GameState::Won => (0, player.time.unwrap_or_default(), 0),
+            GameState::Playing => (1, Duration::default(), 100 - player.percent),
+            GameState::Lost => (2, Duration::default(), 100 - player.percent),
+        });
+        ranking
+    }
+
+    /// Updates the progress of this player, and lets the other players know.
+    fn update_own_progress(&mut self, percent: u8, state: GameState, time: Option<Duration>) {
+        if self.is_host {
+            let host = &mut self.players[0];
+            host.percent = percent;
+            host.state = state;
+            host.time = time;
+            self.broadcast_progress();
+        } else {
+            let mut own = RacePlayer::new(String::new());
+            own.percent = percent;
+            own.state = state;
+            own.time = time;
+            // The host knows which player is on the other end, so the player number and name are left out.
+            self.connection.send(0, &own.to_message(0));
+        }
+    }
+
+    fn broadcast_progress(&mut self) {
+        for (player, progress) in self.players.iter().enumerate() {
+            self.connection.broadcast(&progress.to_message(player));
+        }
+    }
+
+    /// Handles the messages that have arrived from the other players.
+    fn poll(&mut self) {
+        let messages = self.connection.receive();
+        if messages.is_empty() {
+            return;
+        }
+        for (peer, message) in messages {
+            if let Message::Progress {
+                player,
+                percent,
+                state,
+                time_ms,
+                name,
+            } = message
+            {
+                let time = time_ms.map(Duration::from_millis);
+                if self.is_host {
+                    // Peer i is player i + 1, since the host is player 0.
+                    let player = &mut self.players[peer + 1];
+                    player.percent = percent;
+                    player.state = state;
+                    player.time = time;
+                } else {
+                    if self.players.len() <= player {
+                        self.players
+                            .resize(player + 1, RacePlayer::new(String::new()));
+                    }
+                    self.players[player] = RacePlayer {
+                        name,
+                        percent,
+                        state,
+                        time,
+                    };
+                }
+            }
+        }
+        if self.is_host {
+            self.broadcast_progress();
+        }
+    }
+}
+
+impl GameContainer {
+    /// Starts a race game by revealing the common start tile, which also starts the clock.
+    pub(crate) fn start_race(&mut self, race: Race) -> Result<(), MineDistributionError> {
+        let start_tile = race.start_tile;
+        self.mode = GameMode::Race(race);
+        self.record(0, Action::Reveal(start_tile))?;
+        self.report_progress();
+        Ok(())
+    }
+
+    /// Lets the other players of a race know how far this player has come.
+    pub(crate) fn report_progress(&mut self) {
+        let safe_tiles = (self.board.active_tiles() - self.mine_tiles).max(1);
+        let percent = (self.tiles_revealed * 100 / safe_tiles) as u8;
+        let (state, time) = (self.state, self.stats.final_time);
+        if let GameMode::Race(race) = &mut self.mode {
+            race.update_own_progress(percent, state, time);
+        }
+    }
+
+    /// Handles the progress updates of the other players of a race.
+    pub(crate) fn poll_race(&mut self) {
+        if let GameMode::Race(race) = &mut self.mode {
+            race.poll();
+        }
+    }
+}
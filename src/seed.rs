@@ -0,0 +1,41 @@
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+/// The 64-bit FNV-1a hash of the given bytes.
+///
+/// Used instead of the standard library's hasher, whose output may change between Rust releases.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// The random number generator of a seeded game.
+///
+/// ChaCha8 is used instead of `StdRng`, whose algorithm may change between versions of rand,
+/// so that a seed gives the same board on every build and platform.
+pub fn seeded_rng(seed: &str) -> ChaCha8Rng {
+    ChaCha8Rng::seed_from_u64(fnv1a(seed.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::RngCore;
+
+    #[test]
+    fn fnv1a_matches_the_reference_values() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn seeds_give_the_same_numbers_on_every_build() {
+        let mut rng = seeded_rng("daily 2021-03-01 Beginner");
+        assert_eq!(rng.next_u64(), 16_557_920_039_939_043_244);
+    }
+}
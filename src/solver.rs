@@ -0,0 +1,453 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::{
+    board::Board,
+    topology::{Neighbourhood, Topology},
+};
+
+/// The most hidden tiles looked at together when working out whether a tile is safe.
+/// Numbers further away are left out, which can only make the solver miss a deduction, never make a wrong one.
+const MAX_TILES: usize = 40;
+/// The most tiles the search for a mine may try, for a single tile, before giving up on it.
+const MAX_STEPS: usize = 100_000;
+
+/// The rules that the numbers on a board follow.
+#[derive(Copy, Clone, Debug)]
+pub struct Rules<'a> {
+    pub topology: &'a Topology,
+    pub neighbourhood: Neighbourhood,
+    pub max_mines_per_tile: u8,
+    /// The size of the regions that each have one lying number, or `None` if the numbers don't lie.
+    pub liar_region: Option<usize>,
+}
+
+/// A revealed number, which is a rule for the mines on its hidden neighbours.
+struct Number {
+    /// The hidden neighbours.
+    tiles: Vec<usize>,
+    /// The number that is shown. 0 for empty tiles.
+    shown: u8,
+    /// The liar region, if the number could be its lie.
+    region: Option<usize>,
+}
+
+impl Number {
+    /// Whether the hidden neighbours could hold `mines` mines, which is a lie unless it is the shown number.
+    fn allows(&self, mines: usize, lie_allowed: bool) -> bool {
+        mines == self.shown as usize || (lie_allowed && self.lies_about(mines))
+    }
+
+    /// Whether showing this number for `mines` mines would be a lie. A number is only off by one,
+    /// a lying 1 always shows 2 and empty tiles never lie.
+    fn lies_about(&self, mines: usize) -> bool {
+        let shown = self.shown as usize;
+        self.region.is_some()
+            && shown > 0
+            && (mines == shown + 1 || (shown >= 2 && mines == shown - 1))
+    }
+}
+
+/// The hidden tiles that can't hold a mine, worked out from the numbers the player sees.
+/// Flags are ignored, as they could be wrong, and so are the real mines and numbers of hidden tiles.
+///
+/// In a liar game, every region holds exactly one lying number. Until all tiles of a region are revealed,
+/// the lie could be on a hidden tile, so the revealed numbers of the region have at most one lie between them.
+pub fn safe_tiles(board: &Board, rules: Rules) -> Vec<usize> {
+    let is_hidden = |tile: usize| board[tile].is_active() && !board[tile].is_revealed();
+
+    let mut regions: HashMap<(i64, i64), usize> = HashMap::new();
+    let mut region_of = |tile: usize| {
+        rules.liar_region.map(|size| {
+            let key = rules.topology.liar_region(board, tile, size);
+            let next = regions.len();
+            *regions.entry(key).or_insert(next)
+        })
+    };
+    let mut numbers = Vec::new();
+    // Whether each region has a hidden tile, which could be its lie.
+    let mut hidden_regions = Vec::new();
+    for tile in 0..board.len() {
+        if !board[tile].is_active() {
+            continue;
+        }
+        let region = region_of(tile);
+        if let Some(region) = region {
+            if region == hidden_regions.len() {
+                hidden_regions.push(false);
+            }
+            hidden_regions[region] |= is_hidden(tile);
+        }
+        if is_hidden(tile) || board[tile].is_a_mine() {
+            continue;
+        }
+        let shown = board[tile].displayed_number().unwrap_or(0);
+        let tiles: Vec<usize> = rules
+            .topology
+            .neighbours(board, tile, rules.neighbourhood)
+            .into_iter()
+            .filter(|&neighbour| is_hidden(neighbour))
+            .collect();
+        if !tiles.is_empty() {
+            numbers.push(Number {
+                tiles,
+                shown,
+                region: region.filter(|_| shown > 0),
+            });
+        }
+    }
+
+    let solver = Solver {
+        numbers,
+        exactly_one_lie: hidden_regions.iter().map(|&hidden| !hidden).collect(),
+        max_mines: rules.max_mines_per_tile,
+    };
+    solver.safe_tiles()
+}
+
+struct Solver {
+    numbers: Vec<Number>,
+    /// Whether each region is fully revealed, so that one of its revealed numbers has to be the lie.
+    exactly_one_lie: Vec<bool>,
+    max_mines: u8,
+}
+
+impl Solver {
+    fn safe_tiles(&self) -> Vec<usize> {
+        let mut numbers_of: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (index, number) in self.numbers.iter().enumerate() {
+            for &tile in &number.tiles {
+                numbers_of.entry(tile).or_default().push(index);
+            }
+        }
+        let mut region_numbers: Vec<Vec<usize>> = vec![Vec::new(); self.exactly_one_lie.len()];
+        for (index, number) in self.numbers.iter().enumerate() {
+            if let Some(region) = number.region {
+                region_numbers[region].push(index);
+            }
+        }
+
+        let mut candidates: Vec<usize> = numbers_of.keys().copied().collect();
+        candidates.sort_unstable();
+        let mut could_be_mine = vec![false; candidates.len()];
+        let mut safe = Vec::new();
+        for (index, &tile) in candidates.iter().enumerate() {
+            if could_be_mine[index] {
+                continue;
+            }
+            match self.find_mine(tile, &numbers_of, &region_numbers) {
+                Some(mines) => {
+                    for mine in mines {
+                        if let Ok(index) = candidates.binary_search(&mine) {
+                            could_be_mine[index] = true;
+                        }
+                    }
+                }
+                None => safe.push(tile),
+            }
+        }
+        safe
+    }
+
+    /// Looks for a way the mines could lie with a mine on `tile`, going by the numbers around it.
+    /// Returns the tiles that hold a mine in it, or `None` if there is no such way.
+    /// If the search takes too long, the tile is treated as if it could be a mine.
+    fn find_mine(
+        &self,
+        tile: usize,
+        numbers_of: &HashMap<usize, Vec<usize>>,
+        region_numbers: &[Vec<usize>],
+    ) -> Option<Vec<usize>> {
+        // The nearby numbers and their tiles, with `tile` first.
+        let mut tiles = vec![tile];
+        let mut seen: HashSet<usize> = tiles.iter().copied().collect();
+        let mut numbers = Vec::new();
+        let mut included: HashSet<usize> = HashSet::new();
+        let mut queue: VecDeque<usize> = numbers_of[&tile].iter().copied().collect();
+        while let Some(number) = queue.pop_front() {
+            if included.contains(&number) {
+                continue;
+            }
+            let new_tiles = self.numbers[number]
+                .tiles
+                .iter()
+                .filter(|tile| !seen.contains(tile))
+                .count();
+            if tiles.len() + new_tiles > MAX_TILES {
+                continue;
+            }
+            included.insert(number);
+            numbers.push(number);
+            for &neighbour in &self.numbers[number].tiles {
+                if seen.insert(neighbour) {
+                    tiles.push(neighbour);
+                    queue.extend(numbers_of[&neighbour].iter().copied());
+                }
+            }
+            if let Some(region) = self.numbers[number].region {
+                queue.extend(region_numbers[region].iter().copied());
+            }
+        }
+
+        // The search numbers the tiles, numbers and regions it looks at from 0.
+        let local_tiles: HashMap<usize, usize> = tiles
+            .iter()
+            .enumerate()
+            .map(|(index, &tile)| (tile, index))
+            .collect();
+        let mut regions: Vec<usize> = numbers
+            .iter()
+            .filter_map(|&number| self.numbers[number].region)
+            .collect();
+        regions.sort_unstable();
+        regions.dedup();
+        let mut numbers_of_tile = vec![Vec::new(); tiles.len()];
+        for (index, &number) in numbers.iter().enumerate() {
+            for tile in &self.numbers[number].tiles {
+                numbers_of_tile[local_tiles[tile]].push(index);
+            }
+        }
+
+        let mut search = Search {
+            max_mines: self.max_mines,
+            numbers: numbers
+                .iter()
+                .map(|&number| &self.numbers[number])
+                .collect(),
+            regions: numbers
+                .iter()
+                .map(|&number| {
+                    self.numbers[number]
+                        .region
+                        .map(|region| regions.binary_search(&region).unwrap())
+                })
+                .collect(),
+            numbers_of_tile,
+            mines: Vec::new(),
+            sums: vec![0; numbers.len()],
+            unknown: numbers
+                .iter()
+                .map(|&number| self.numbers[number].tiles.len())
+                .collect(),
+            lies: vec![0; regions.len()],
+            // A region can only be held to exactly one lie if all of its numbers are looked at.
+            exactly_one_lie: regions
+                .iter()
+                .map(|&region| {
+                    self.exactly_one_lie[region]
+                        && region_numbers[region]
+                            .iter()
+                            .all(|number| included.contains(number))
+                })
+                .collect(),
+            steps: 0,
+        };
+        match search.run() {
+            Outcome::Found | Outcome::GaveUp => Some(
+                tiles
+                    .iter()
+                    .zip(&search.mines)
+                    .filter(|&(_, &mines)| mines > 0)
+                    .map(|(&tile, _)| tile)
+                    .collect(),
+            ),
+            Outcome::Impossible => None,
+        }
+    }
+}
+
+enum Outcome {
+    Found,
+    Impossible,
+    GaveUp,
+}
+
+/// A depth-first search over the mines on a few tiles, the first of which holds at least one mine.
+/// Tiles, numbers and regions are numbered from 0 for the search.
+struct Search<'a> {
+    max_mines: u8,
+    numbers: Vec<&'a Number>,
+    /// The region of each number, if it could lie.
+    regions: Vec<Option<usize>>,
+    /// The numbers around each tile.
+    numbers_of_tile: Vec<Vec<usize>>,
+    /// The mines on the tiles decided so far.
+    mines: Vec<u8>,
+    /// The mines around each number so far.
+    sums: Vec<usize>,
+    /// The tiles around each number that haven't been decided yet.
+    unknown: Vec<usize>,
+    /// The lies found in each region so far.
+    lies: Vec<u8>,
+    exactly_one_lie: Vec<bool>,
+    steps: usize,
+}
+
+impl Search<'_> {
+    fn run(&mut self) -> Outcome {
+        if self.mines.len() == self.numbers_of_tile.len() {
+            let lies_add_up = self
+                .exactly_one_lie
+                .iter()
+                .zip(&self.lies)
+                .all(|(&exactly_one, &lies)| !exactly_one || lies == 1);
+            return if lies_add_up {
+                Outcome::Found
+            } else {
+                Outcome::Impossible
+            };
+        }
+        let tile = self.mines.len();
+        let least = if tile == 0 { 1 } else { 0 };
+        for mines in least..=self.max_mines {
+            self.steps += 1;
+            if self.steps > MAX_STEPS {
+                return Outcome::GaveUp;
+            }
+            self.mines.push(mines);
+            let mut fits = true;
+            for index in 0..self.numbers_of_tile[tile].len() {
+                let number = self.numbers_of_tile[tile][index];
+                self.sums[number] += mines as usize;
+                self.unknown[number] -= 1;
+                fits &= self.still_fits(number);
+            }
+            if fits {
+                match self.run() {
+                    Outcome::Impossible => {}
+                    outcome => return outcome,
+                }
+            }
+            self.mines.pop();
+            for index in 0..self.numbers_of_tile[tile].len() {
+                let number = self.numbers_of_tile[tile][index];
+                if self.unknown[number] == 0 {
+                    self.forget_lie(number);
+                }
+                self.sums[number] -= mines as usize;
+                self.unknown[number] += 1;
+            }
+        }
+        Outcome::Impossible
+    }
+
+    /// Whether the number can still be right about the tiles decided so far.
+    /// Once all its tiles are decided, a lie is counted towards its region.
+    fn still_fits(&mut self, index: usize) -> bool {
+        let number = self.numbers[index];
+        let region = self.regions[index];
+        let lie_allowed = matches!(region, Some(region) if self.lies[region] == 0);
+        let (sum, unknown) = (self.sums[index], self.unknown[index]);
+        if unknown > 0 {
+            let most = sum + unknown * self.max_mines as usize;
+            return (sum..=most).any(|mines| number.allows(mines, lie_allowed));
+        }
+        // The lie is counted even if it breaks the rules, since backing out of the tile takes it back either way.
+        if let Some(region) = region.filter(|_| number.lies_about(sum)) {
+            self.lies[region] += 1;
+        }
+        number.allows(sum, lie_allowed)
+    }
+
+    fn forget_lie(&mut self, index: usize) {
+        let sum = self.sums[index];
+        if let Some(region) = self.regions[index].filter(|_| self.numbers[index].lies_about(sum)) {
+            self.lies[region] -= 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reveals the bottom row of a board with two rows, and fills in its numbers.
+    fn board_with_bottom_row(cols: usize, mines: &[usize]) -> Board {
+        let mut board = Board::new(cols, 2);
+        for &mine in mines {
+            board[mine].set_mines(1);
+        }
+        board.set_numbers(&Topology::Square, Neighbourhood::Adjacent);
+        for x in 0..cols {
+            let tile = board.index_of((x, 1));
+            board[tile].set_revealed(true);
+        }
+        board
+    }
+
+    fn rules(liar_region: Option<usize>) -> Rules<'static> {
+        Rules {
+            topology: &Topology::Square,
+            neighbourhood: Neighbourhood::Adjacent,
+            max_mines_per_tile: 1,
+            liar_region,
+        }
+    }
+
+    #[test]
+    fn numbers_are_read_as_shown() {
+        // Shows 0 1 1 1 0 under a mine in the middle of the top row.
+        let board = board_with_bottom_row(5, &[2]);
+        assert_eq!(safe_tiles(&board, rules(None)), vec![0, 1, 3, 4]);
+    }
+
+    #[test]
+    fn guesses_are_not_deductions() {
+        // Shows 1 1 under one mine, which could be on either tile.
+        let board = board_with_bottom_row(2, &[0]);
+        assert!(safe_tiles(&board, rules(None)).is_empty());
+    }
+
+    #[test]
+    fn hidden_mines_and_numbers_are_not_looked_at() {
+        let mut board = board_with_bottom_row(2, &[0]);
+        board[0].set_mines(0);
+        board[1].set_mines(1);
+        assert!(safe_tiles(&board, rules(None)).is_empty());
+    }
+
+    #[test]
+    fn any_revealed_number_of_a_region_could_lie() {
+        // Shows 1 1 1 under a mine in the middle, which are only all true if the outer tiles are safe.
+        // The middle number could be a lying 2 with mines on both outer tiles, if the lie of the region
+        // isn't one of the hidden 1s.
+        let mut board = board_with_bottom_row(3, &[1]);
+        board[0].set_lie(1);
+        assert_eq!(safe_tiles(&board, rules(None)), vec![0, 2]);
+        assert!(safe_tiles(&board, rules(Some(3))).is_empty());
+    }
+
+    #[test]
+    fn one_lie_is_allowed_for() {
+        // Shows 0 1 2 1 0 under a mine in the middle, where the 2 is the lie.
+        // Both empty tiles are true, which leaves only one way for the numbers to work out.
+        let mut board = board_with_bottom_row(5, &[2]);
+        let middle = board.index_of((2, 1));
+        board[middle].set_lie(1);
+        assert_eq!(safe_tiles(&board, rules(Some(5))), vec![0, 1, 3, 4]);
+    }
+
+    #[test]
+    fn a_revealed_region_has_exactly_one_lie() {
+        // Two revealed 2s in different regions: the left one around both hidden tiles, the right one
+        // only around the middle tile. The right 2 has to be a lying 1, so the middle tile is a mine.
+        // The left 2 is alone in a region without hidden tiles, so it is the lie of its region,
+        // and the other hidden tile is safe.
+        let topology = Topology::Graph {
+            positions: vec![(0.0, 0.0), (3.0, 0.0), (6.0, 0.0), (7.0, 0.0)],
+            neighbours: vec![vec![1, 2], vec![0, 3], vec![0], vec![1]],
+        };
+        let mut board = Board::new(4, 1);
+        board[1].set_mines(1);
+        board.set_numbers(&topology, Neighbourhood::Adjacent);
+        for &tile in &[0, 3] {
+            board[tile].set_revealed(true);
+            board[tile].set_lie(1);
+        }
+        let rules = Rules {
+            topology: &topology,
+            liar_region: Some(3),
+            ..rules(None)
+        };
+        assert_eq!(safe_tiles(&board, rules), vec![2]);
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        mpsc::{self, SyncSender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    board::MineDistributionError,
+    game::{Action, GameContainer, GameMode, GameSettings},
+    net::{Connection, Message},
+    topology::Topology,
+};
+
+/// The port spectators connect to, unless another one is chosen.
+pub(crate) const SPECTATOR_PORT: u16 = 7879;
+
+/// How often the state of the game is sent to the spectators while nothing happens, so that their clocks stay in sync.
+const STATE_INTERVAL: Duration = Duration::from_secs(1);
+/// The most lines that can wait to be sent to a spectator. A spectator that falls further behind is dropped.
+const SPECTATOR_BACKLOG: usize = 1024;
+
+/// The spectators watching a game, who are streamed its action log as it grows.
+///
+/// A spectator gets the settings of the game, including its seed, and replays the actions on a board of its own.
+/// Since the mines only depend on the seed and the first click, that board is identical to the watched one.
+pub(crate) struct Spectators {
+    /// The settings of the watched game, as sent to every spectator that connects.
+    game: Message,
+    /// Spectators that have connected, but haven't been caught up with the game yet.
+    joined: Arc<Mutex<Vec<TcpStream>>>,
+    /// The lines to send to each spectator, which are written by a thread of its own,
+    /// so that a slow spectator can't hold up the game.
+    streams: Vec<SyncSender<String>>,
+    /// When the state of the game was last sent.
+    state_sent: Instant,
+}
+
+impl Spectators {
+    /// Starts accepting spectators of a game with the given settings on the given port, on all network interfaces.
+    ///
+    /// Only games that can be described to a spectator can be watched, i.e. seeded games on regular boards.
+    pub(crate) fn host(port: u16, settings: &GameSettings) -> io::Result<Spectators> {
+        if settings.seed.is_none()
+            || settings.mask.is_some()
+            || matches!(settings.topology, Topology::Graph { .. })
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Only seeded games on regular boards can be watched",
+            ));
+        }
+        let listener = TcpListener::bind(("0.0.0.0", port))?;
+        let joined = Arc::new(Mutex::new(Vec::new()));
+        let waiting = Arc::clone(&joined);
+        thread::spawn(move || {
+            for stream in listener.incoming().filter_map(Result::ok) {
+                waiting.lock().unwrap().push(stream);
+            }
+        });
+        Ok(Spectators {
+            game: Message::Game(settings.clone()),
+            joined,
+            streams: Vec::new(),
+            state_sent: Instant::now(),
+        })
+    }
+
+    /// Sends a message to every spectator. Spectators that have disconnected or fallen too far behind are dropped.
+    fn broadcast(&mut self, message: &Message) {
+        let line = message.to_line();
+        self.streams
+            .retain(|stream| stream.try_send(line.clone()).is_ok());
+    }
+
+    /// Sends an action taken in the game to every spectator, followed by the state of the game after it.
+    pub(crate) fn stream(&mut self, player: usize, action: Action, state: &Message) {
+        self.broadcast(&Message::Action { player, action });
+        self.broadcast(state);
+        self.state_sent = Instant::now();
+    }
+}
+
+/// Starts writing lines to a spectator on a thread of its own. The thread stops once the spectator disconnects,
+/// or once the returned sender is dropped.
+fn spawn_writer(mut stream: TcpStream) -> SyncSender<String> {
+    let (sender, lines) = mpsc::sync_channel::<String>(SPECTATOR_BACKLOG);
+    thread::spawn(move || {
+        for line in lines {
+            if stream.write_all(line.as_bytes()).is_err() {
+                return;
+            }
+        }
+    });
+    sender
+}
+
+/// A game watched from another instance, whose actions are replayed as they arrive.
+pub(crate) struct Spectating {
+    connection: Connection,
+    /// The elapsed time of the watched game as last received, and when it was received.
+    pub(crate) player_elapsed: (Duration, Instant),
+}
+
+/// Connects to the game being played at the given address.
+///
+/// Returns the settings of the game, and the spectator that follows it.
+pub(crate) fn spectate(address: &str) -> io::Result<(GameSettings, Spectating)> {
+    let address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Invalid address"))?;
+    let stream = TcpStream::connect_timeout(&address, Duration::from_secs(5))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    match Message::parse(&line) {
+        Some(Message::Game(settings)) => {
+            let spectating = Spectating {
+                connection: Connection::new(vec![(stream, reader)]),
+                player_elapsed: (Duration::default(), Instant::now()),
+            };
+            Ok((settings, spectating))
+        }
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The game can't be watched",
+        )),
+    }
+}
+
+impl GameContainer {
+    /// The amount of reveals, flags and chords in the action log.
+    pub(crate) fn click_counts(&self) -> (usize, usize, usize) {
+        self.log.iter().fold(
+            (0, 0, 0),
+            |(reveals, flags, chords), (_, action)| match action {
+                Action::Reveal(_) => (reveals + 1, flags, chords),
+                Action::Flag(_) => (reveals, flags + 1, chords),
+                Action::Chord(_) => (reveals, flags, chords + 1),
+            },
+        )
+    }
+
+    /// The state of the game, as sent to the spectators to keep their clocks in sync.
+    pub(crate) fn state_message(&self) -> Message {
+        Message::State {
+            state: self.state,
+            flags: self.tiles_flagged,
+            elapsed_ms: self.clock.elapsed().as_millis() as u64,
+        }
+    }
+
+    /// Catches up spectators that have just connected, and keeps the clocks of the others in sync.
+    /// A spectator replays the actions that have arrived from the watched game.
+    pub(crate) fn poll_spectators(&mut self) -> Result<(), MineDistributionError> {
+        let state = self.state_message();
+        if let GameMode::Watched(spectators) = &mut self.mode {
+            let joined = std::mem::take(&mut *spectators.joined.lock().unwrap());
+            for stream in joined {
+                let mut catch_up = spectators.game.to_line();
+                for &(player, action) in self.log.iter() {
+                    catch_up.push_str(&Message::Action { player, action }.to_line());
+                }
+                catch_up.push_str(&state.to_line());
+                let writer = spawn_writer(stream);
+                if writer.try_send(catch_up).is_ok() {
+                    spectators.streams.push(writer);
+                }
+            }
+            if spectators.state_sent.elapsed() >= STATE_INTERVAL {
+                spectators.state_sent = Instant::now();
+                spectators.broadcast(&state);
+            }
+        }
+
+        let messages = match &self.mode {
+            GameMode::Spectate(spectating) => spectating.connection.receive(),
+            _ => return Ok(()),
+        };
+        let mut result = Ok(());
+        for (_, message) in messages {
+            match message {
+                Message::Action { player, action } if action.tile() < self.board.len() => {
+                    self.log.push((player, action));
+                    result = result.and(self.perform(action));
+                }
+                Message::State { elapsed_ms, .. } => {
+                    if let GameMode::Spectate(spectating) = &mut self.mode {
+                        spectating.player_elapsed =
+                            (Duration::from_millis(elapsed_ms), Instant::now());
+                    }
+                }
+                _ => {}
+            }
+        }
+        result
+    }
+}
@@ -0,0 +1,358 @@
+use std::{
+    collections::BTreeSet,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use directories::ProjectDirs;
+use ggez::{
+    graphics::{Color, Image, Rect},
+    Context, GameError, GameResult,
+};
+use serde::Deserialize;
+
+use crate::{
+    board::{Mark, Tile},
+    topology::Topology,
+};
+
+/// The name of the manifest of a theme, in the theme's directory.
+const MANIFEST: &str = "theme.toml";
+
+/// The contents of a theme manifest.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    name: String,
+    tile_size: u32,
+    background: [u8; 3],
+    text: [u8; 3],
+    spritesheets: SpritesheetFiles,
+    sprites: SpritePositions,
+}
+
+/// The spritesheet for each shape of tiles, relative to the theme directory.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpritesheetFiles {
+    square: String,
+    hex: String,
+    triangle: String,
+}
+
+/// The column of each sprite on the spritesheets, counted from 0. Every spritesheet of a theme has the same layout.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SpritePositions {
+    empty: usize,
+    hidden: usize,
+    flag: usize,
+    question: usize,
+    /// The numbers from 1 and up. Numbers without a sprite are drawn as text on an empty tile.
+    numbers: Vec<usize>,
+    /// Two and three stacked flags.
+    stacked_flags: [usize; 2],
+    /// The mines shown when a game is lost, and the one that was hit.
+    mine: usize,
+    exploded_mine: usize,
+    /// A flag on a tile without a mine, shown when a game is lost.
+    wrong_flag: usize,
+    /// A hidden tile held down by the mouse.
+    pressed: usize,
+}
+
+impl SpritePositions {
+    /// The positions, by the name of their sprite.
+    fn named(&self) -> Vec<(String, usize)> {
+        let mut named = vec![
+            ("empty".to_string(), self.empty),
+            ("hidden".to_string(), self.hidden),
+            ("flag".to_string(), self.flag),
+            ("question".to_string(), self.question),
+        ];
+        for (number, &column) in self.numbers.iter().enumerate() {
+            named.push((format!("number {}", number + 1), column));
+        }
+        for (flags, &column) in self.stacked_flags.iter().enumerate() {
+            named.push((format!("{} stacked flags", flags + 2), column));
+        }
+        named.push(("mine".to_string(), self.mine));
+        named.push(("exploded mine".to_string(), self.exploded_mine));
+        named.push(("wrong flag".to_string(), self.wrong_flag));
+        named.push(("pressed".to_string(), self.pressed));
+        named
+    }
+}
+
+/// A sprite of a theme.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Sprite {
+    Empty,
+    Hidden,
+    /// A hidden tile with the given amount of flags on it, from 1 to 3.
+    Flags(u8),
+    Question,
+    Number(u8),
+    Mine,
+    ExplodedMine,
+    WrongFlag,
+    Pressed,
+}
+
+impl Sprite {
+    /// The sprite a tile is drawn with. A lost game shows where the mines were, and which flags were wrong.
+    pub(crate) fn of(tile: Tile, lost: bool) -> Sprite {
+        match (tile.is_revealed(), tile.mark(), tile.displayed_number()) {
+            (false, Mark::Flag, _) if lost && tile.flags() > tile.mines() => Sprite::WrongFlag,
+            (false, Mark::Flag, _) => Sprite::Flags(tile.flags()),
+            (false, _, _) if lost && tile.is_a_mine() => Sprite::Mine,
+            (false, Mark::None, _) => Sprite::Hidden,
+            (false, Mark::Question, _) => Sprite::Question,
+            (true, _, Some(number)) => Sprite::Number(number),
+            (true, _, None) => Sprite::Empty,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Spritesheet {
+    path: PathBuf,
+    /// The amount of sprites across.
+    columns: usize,
+}
+
+/// The look of the game: a spritesheet for each shape of tiles, and the colours around the board.
+///
+/// A theme is a directory with a `theme.toml` manifest, which names the spritesheets and tells where each sprite is
+/// on them. The spritesheets are a row of sprites of `tile_size` pixels, or two rows for triangles, which have a row
+/// for each orientation. Themes are checked when they are loaded, so that a broken theme is reported up front.
+#[derive(Clone, Debug)]
+pub(crate) struct Theme {
+    pub(crate) name: String,
+    /// The width and height of a sprite, in pixels.
+    pub(crate) tile_size: f32,
+    pub(crate) background: Color,
+    pub(crate) text: Color,
+    sprites: SpritePositions,
+    square: Spritesheet,
+    hex: Spritesheet,
+    triangle: Spritesheet,
+}
+
+impl Theme {
+    /// Loads the theme with the given name, which is the name of its directory.
+    /// The themes of the user are looked for first, so that they can replace the built-in themes.
+    pub(crate) fn load(name: &str) -> Result<Theme, ThemeError> {
+        theme_dirs()
+            .into_iter()
+            .map(|dir| dir.join(name))
+            .find(|dir| dir.join(MANIFEST).is_file())
+            .ok_or_else(|| ThemeError::NotFound(name.to_string()))
+            .and_then(|dir| Theme::load_dir(&dir))
+    }
+
+    /// Loads the theme in the given directory.
+    fn load_dir(dir: &Path) -> Result<Theme, ThemeError> {
+        let manifest = fs::read_to_string(dir.join(MANIFEST)).map_err(ThemeError::Io)?;
+        let manifest: Manifest = toml::from_str(&manifest).map_err(ThemeError::Manifest)?;
+        let tile_size = manifest.tile_size;
+        if tile_size == 0 {
+            return Err(ThemeError::TileSize);
+        }
+        if manifest.sprites.numbers.len() < 8 {
+            return Err(ThemeError::TooFewNumbers(manifest.sprites.numbers.len()));
+        }
+
+        let spritesheet = |file: &str, rows: u32| -> Result<Spritesheet, ThemeError> {
+            let path = dir.join(file);
+            let (width, height) =
+                image::image_dimensions(&path).map_err(|error| ThemeError::Image {
+                    file: file.to_string(),
+                    error,
+                })?;
+            if width == 0 || width % tile_size != 0 || height != tile_size * rows {
+                return Err(ThemeError::SpritesheetSize {
+                    file: file.to_string(),
+                    size: (width, height),
+                    rows,
+                    tile_size,
+                });
+            }
+            Ok(Spritesheet {
+                path,
+                columns: (width / tile_size) as usize,
+            })
+        };
+        let files = &manifest.spritesheets;
+        let square = spritesheet(&files.square, 1)?;
+        let hex = spritesheet(&files.hex, 1)?;
+        let triangle = spritesheet(&files.triangle, Topology::Triangle.sprite_rows() as u32)?;
+
+        let columns = square.columns.min(hex.columns).min(triangle.columns);
+        for (sprite, column) in manifest.sprites.named() {
+            if column >= columns {
+                return Err(ThemeError::SpriteOutOfRange {
+                    sprite,
+                    column,
+                    columns,
+                });
+            }
+        }
+
+        let colour = |[r, g, b]: [u8; 3]| Color::from_rgb(r, g, b);
+        Ok(Theme {
+            name: manifest.name,
+            tile_size: tile_size as f32,
+            background: colour(manifest.background),
+            text: colour(manifest.text),
+            sprites: manifest.sprites,
+            square,
+            hex,
+            triangle,
+        })
+    }
+
+    fn spritesheet_of(&self, topology: &Topology) -> &Spritesheet {
+        match topology {
+            Topology::Square | Topology::Torus | Topology::Cube { .. } | Topology::Graph { .. } => {
+                &self.square
+            }
+            Topology::Hex => &self.hex,
+            Topology::Triangle => &self.triangle,
+        }
+    }
+
+    /// The spritesheet for boards of the given topology.
+    pub(crate) fn spritesheet(&self, ctx: &mut Context, topology: &Topology) -> GameResult<Image> {
+        let image = image::open(&self.spritesheet_of(topology).path)
+            .map_err(|e| GameError::ResourceLoadError(e.to_string()))?
+            .to_rgba();
+        let (width, height) = (image.width() as u16, image.height() as u16);
+        Image::from_rgba8(ctx, width, height, &image.into_raw())
+    }
+
+    /// Whether the theme has a sprite for the given number.
+    pub(crate) fn has_number_sprite(&self, number: u8) -> bool {
+        number >= 1 && number as usize <= self.sprites.numbers.len()
+    }
+
+    /// The part of the spritesheet of the given topology which holds the sprite, in the first row of sprites.
+    pub(crate) fn source(&self, topology: &Topology, sprite: Sprite) -> Rect {
+        let sprites = &self.sprites;
+        let column = match sprite {
+            Sprite::Empty => sprites.empty,
+            Sprite::Hidden => sprites.hidden,
+            Sprite::Flags(0) | Sprite::Flags(1) => sprites.flag,
+            Sprite::Flags(2) => sprites.stacked_flags[0],
+            Sprite::Flags(_) => sprites.stacked_flags[1],
+            Sprite::Question => sprites.question,
+            Sprite::Number(number) => sprites
+                .numbers
+                .get((number as usize).wrapping_sub(1))
+                .copied()
+                .unwrap_or(sprites.empty),
+            Sprite::Mine => sprites.mine,
+            Sprite::ExplodedMine => sprites.exploded_mine,
+            Sprite::WrongFlag => sprites.wrong_flag,
+            Sprite::Pressed => sprites.pressed,
+        };
+        let fraction = 1.0 / self.spritesheet_of(topology).columns as f32;
+        Rect::new(column as f32 * fraction, 0.0, fraction, 1.0)
+    }
+}
+
+/// The directories themes are looked for in: the user's theme directory, then the built-in themes.
+fn theme_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(project) = ProjectDirs::from("", "", "rustsweeper") {
+        dirs.push(project.data_dir().join("themes"));
+    }
+    if let Ok(manifest_dir) = std::env::var("CARGO_MANIFEST_DIR") {
+        dirs.push(PathBuf::from(manifest_dir).join("resources").join("themes"));
+    }
+    dirs
+}
+
+/// The names of the themes that can be chosen, in alphabetical order. The themes aren't checked.
+pub(crate) fn available_themes() -> Vec<String> {
+    let mut names = BTreeSet::new();
+    for dir in theme_dirs() {
+        for entry in fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .filter_map(Result::ok)
+        {
+            if entry.path().join(MANIFEST).is_file() {
+                names.insert(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+    }
+    names.into_iter().collect()
+}
+
+/// Errors that can occur when loading a theme.
+#[derive(Debug)]
+pub(crate) enum ThemeError {
+    NotFound(String),
+    Io(io::Error),
+    Manifest(toml::de::Error),
+    Image {
+        file: String,
+        error: image::ImageError,
+    },
+    TileSize,
+    TooFewNumbers(usize),
+    /// A spritesheet that isn't a whole amount of sprites across, or doesn't have the expected rows of sprites.
+    SpritesheetSize {
+        file: String,
+        size: (u32, u32),
+        rows: u32,
+        tile_size: u32,
+    },
+    /// A sprite that is placed beyond the end of a spritesheet.
+    SpriteOutOfRange {
+        sprite: String,
+        column: usize,
+        columns: usize,
+    },
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeError::NotFound(name) => write!(f, "There is no theme called \"{}\"", name),
+            ThemeError::Io(e) => write!(f, "Could not read the theme manifest: {}", e),
+            ThemeError::Manifest(e) => write!(f, "Invalid theme manifest: {}", e),
+            ThemeError::Image { file, error } => {
+                write!(f, "Could not read the spritesheet {}: {}", file, error)
+            }
+            ThemeError::TileSize => write!(f, "Invalid theme: the tile size must be at least 1"),
+            ThemeError::TooFewNumbers(numbers) => write!(
+                f,
+                "Invalid theme: it has sprites for {} numbers, but needs at least 8",
+                numbers
+            ),
+            ThemeError::SpritesheetSize {
+                file,
+                size: (width, height),
+                rows,
+                tile_size,
+            } => write!(
+                f,
+                "Invalid theme: the spritesheet {} is {}x{} pixels, but should be {} rows of {}x{} sprites",
+                file, width, height, rows, tile_size, tile_size
+            ),
+            ThemeError::SpriteOutOfRange {
+                sprite,
+                column,
+                columns,
+            } => write!(
+                f,
+                "Invalid theme: the {} sprite is in column {}, but the spritesheets only have {} columns",
+                sprite, column, columns
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {}
@@ -0,0 +1,674 @@
+use std::{fmt, fs, path::Path};
+
+use crate::board::{Board, Tile};
+
+/// Decides which tiles of the board neighbour each other, and how the tiles are laid out on the screen.
+///
+/// The neighbours of a tile are the tiles that count towards its number, that are revealed when it is opened
+/// and that are revealed when it is chorded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Topology {
+    /// A rectangular grid, where each tile borders the (up to) 8 tiles surrounding it, or another `Neighbourhood`.
+    Square,
+    /// A rectangular grid whose edges wrap around, so that e.g. the first column borders the last one.
+    Torus,
+    /// A grid of pointy-topped hexagons, where each tile borders (up to) 6 tiles.
+    /// Every odd row is shifted half a tile to the right.
+    Hex,
+    /// A grid of alternately upwards and downwards pointing triangles,
+    /// where each tile borders the (up to) 12 tiles it shares a corner with.
+    /// The tile at (x, y) points upwards if `x + y` is even.
+    Triangle,
+    /// A cube of `layers` stacked square grids, where each tile borders the (up to) 26 tiles surrounding it.
+    /// The layers are drawn side by side, one tile apart.
+    Cube { layers: usize },
+    /// An arbitrary graph of tiles, loaded from a file by `Topology::load_graph`.
+    /// The tile with index `i` is drawn as a square at `positions[i]` (in tiles), and borders the tiles in `neighbours[i]`.
+    Graph {
+        positions: Vec<(f32, f32)>,
+        neighbours: Vec<Vec<usize>>,
+    },
+}
+
+/// Which tiles count as neighbours on a square grid, i.e. on a `Square` or `Torus` board.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Neighbourhood {
+    /// The 8 tiles surrounding the tile.
+    Adjacent,
+    /// The 8 tiles a chess knight could move to from the tile.
+    Knight,
+    /// The 24 tiles in the 5x5 area centered on the tile.
+    Radius2,
+}
+
+impl Neighbourhood {
+    /// The offsets from a tile to its neighbours on a square grid.
+    pub fn offsets(self) -> &'static [(isize, isize)] {
+        match self {
+            Neighbourhood::Adjacent => &[
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ],
+            Neighbourhood::Knight => &[
+                (-1, -2),
+                (1, -2),
+                (-2, -1),
+                (2, -1),
+                (-2, 1),
+                (2, 1),
+                (-1, 2),
+                (1, 2),
+            ],
+            Neighbourhood::Radius2 => &[
+                (-2, -2),
+                (-1, -2),
+                (0, -2),
+                (1, -2),
+                (2, -2),
+                (-2, -1),
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (2, -1),
+                (-2, 0),
+                (-1, 0),
+                (1, 0),
+                (2, 0),
+                (-2, 1),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+                (2, 1),
+                (-2, 2),
+                (-1, 2),
+                (0, 2),
+                (1, 2),
+                (2, 2),
+            ],
+        }
+    }
+}
+
+impl fmt::Display for Neighbourhood {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Neighbourhood::Adjacent => "The 8 surrounding tiles",
+            Neighbourhood::Knight => "The 8 tiles a knight's move away",
+            Neighbourhood::Radius2 => "The 24 tiles in the surrounding 5x5 area",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Topology {
+    /// The indices of all tiles neighbouring the given tile, not including the tile itself.
+    ///
+    /// The neighbourhood only applies to square grids, the neighbours on other topologies follow from their shape.
+    pub fn neighbours(
+        &self,
+        board: &Board,
+        tile: usize,
+        neighbourhood: Neighbourhood,
+    ) -> Vec<usize> {
+        match self {
+            Topology::Graph { neighbours, .. } => return neighbours[tile].clone(),
+            Topology::Cube { .. } => return Topology::cube_neighbours(board, tile),
+            _ => (),
+        }
+
+        let (cols, rows) = (board.cols() as isize, board.rows() as isize);
+        let (tile_x, tile_y) = board.coords_of(tile);
+        let (tile_x, tile_y) = (tile_x as isize, tile_y as isize);
+
+        let offsets: &[(isize, isize)] = match self {
+            Topology::Square | Topology::Torus => neighbourhood.offsets(),
+            Topology::Hex if tile_y % 2 == 0 => {
+                &[(-1, -1), (0, -1), (-1, 0), (1, 0), (-1, 1), (0, 1)]
+            }
+            Topology::Hex => &[(0, -1), (1, -1), (-1, 0), (1, 0), (0, 1), (1, 1)],
+            // An upwards pointing triangle shares corners with 3 tiles above it and 5 tiles below it,
+            // and the other way around for a downwards pointing triangle.
+            Topology::Triangle if (tile_x + tile_y) % 2 == 0 => &[
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-2, 0),
+                (-1, 0),
+                (1, 0),
+                (2, 0),
+                (-2, 1),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+                (2, 1),
+            ],
+            Topology::Triangle => &[
+                (-2, -1),
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (2, -1),
+                (-2, 0),
+                (-1, 0),
+                (1, 0),
+                (2, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ],
+            Topology::Cube { .. } | Topology::Graph { .. } => unreachable!(),
+        };
+
+        let mut neighbours = Vec::with_capacity(offsets.len());
+        for (xd, yd) in offsets {
+            let (x, y) = (tile_x + xd, tile_y + yd);
+            let (x, y) = match self {
+                Topology::Torus => (x.rem_euclid(cols), y.rem_euclid(rows)),
+                _ => {
+                    // Makes sure that it doesn't go outside of the board bounds
+                    if x < 0 || x >= cols || y < 0 || y >= rows {
+                        continue;
+                    }
+                    (x, y)
+                }
+            };
+            neighbours.push(board.index_of((x as usize, y as usize)));
+        }
+
+        // On boards narrower than the neighbourhood a wrapped-around tile can be reached in more than one way.
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        neighbours.retain(|&neighbour| neighbour != tile);
+        neighbours
+    }
+
+    /// The most neighbours any tile of a board of this topology can have.
+    pub fn most_neighbours(&self, neighbourhood: Neighbourhood) -> usize {
+        match self {
+            Topology::Square | Topology::Torus => neighbourhood.offsets().len(),
+            Topology::Hex => 6,
+            Topology::Triangle => 12,
+            Topology::Cube { .. } => 26,
+            Topology::Graph { neighbours, .. } => {
+                neighbours.iter().map(Vec::len).max().unwrap_or(0)
+            }
+        }
+    }
+
+    fn cube_neighbours(board: &Board, tile: usize) -> Vec<usize> {
+        let (tile_x, tile_y, tile_z) = board.coords_of_3d(tile);
+        let in_range = |coord: usize, delta: isize, size: usize| {
+            let coord = coord as isize + delta;
+            if coord >= 0 && (coord as usize) < size {
+                Some(coord as usize)
+            } else {
+                None
+            }
+        };
+
+        let mut neighbours = Vec::with_capacity(26);
+        for zd in -1..=1 {
+            for yd in -1..=1 {
+                for xd in -1..=1 {
+                    if (xd, yd, zd) == (0, 0, 0) {
+                        continue;
+                    }
+                    if let (Some(x), Some(y), Some(z)) = (
+                        in_range(tile_x, xd, board.cols()),
+                        in_range(tile_y, yd, board.rows()),
+                        in_range(tile_z, zd, board.layers()),
+                    ) {
+                        neighbours.push(board.index_of_3d((x, y, z)));
+                    }
+                }
+            }
+        }
+        neighbours
+    }
+
+    /// The size of a board with the given dimensions on the screen, in pixels.
+    pub fn board_size(&self, cols: usize, rows: usize, tile_size: f32) -> (f32, f32) {
+        match self {
+            Topology::Square | Topology::Torus => {
+                (cols as f32 * tile_size, rows as f32 * tile_size)
+            }
+            Topology::Hex => (
+                (cols as f32 + 0.5) * tile_size,
+                (rows as f32 * 0.75 + 0.25) * tile_size,
+            ),
+            Topology::Triangle => (
+                (cols as f32 + 1.0) * 0.5 * tile_size,
+                rows as f32 * tile_size,
+            ),
+            Topology::Cube { layers } => (
+                ((cols + 1) * layers - 1) as f32 * tile_size,
+                rows as f32 * tile_size,
+            ),
+            Topology::Graph { positions, .. } => positions
+                .iter()
+                .fold((0.0, 0.0), |(width, height): (f32, f32), &(x, y)| {
+                    (width.max(x + 1.0), height.max(y + 1.0))
+                }),
+        }
+    }
+
+    /// The screen position of the top-left corner of the tile's sprite.
+    pub fn tile_position(&self, board: &Board, tile: usize, tile_size: f32) -> (f32, f32) {
+        let (x, y) = board.coords_of(tile);
+        match self {
+            Topology::Square | Topology::Torus => (x as f32 * tile_size, y as f32 * tile_size),
+            Topology::Hex => (
+                (x as f32 + if y % 2 == 1 { 0.5 } else { 0.0 }) * tile_size,
+                y as f32 * 0.75 * tile_size,
+            ),
+            Topology::Triangle => (x as f32 * 0.5 * tile_size, y as f32 * tile_size),
+            Topology::Cube { .. } => {
+                let (x, y, z) = board.coords_of_3d(tile);
+                (
+                    (z * (board.cols() + 1) + x) as f32 * tile_size,
+                    y as f32 * tile_size,
+                )
+            }
+            Topology::Graph { positions, .. } => {
+                (positions[tile].0 * tile_size, positions[tile].1 * tile_size)
+            }
+        }
+    }
+
+    /// The region of a liar game the tile belongs to, when the board is cut into squares of `region_size` tiles.
+    /// Every region has one lying number.
+    pub fn liar_region(&self, board: &Board, tile: usize, region_size: usize) -> (i64, i64) {
+        let (x, y) = self.tile_position(board, tile, 1.0);
+        (
+            (x / region_size as f32).floor() as i64,
+            (y / region_size as f32).floor() as i64,
+        )
+    }
+
+    /// The index of the tile at the given screen position, if there is one.
+    pub fn tile_at(&self, board: &Board, (x, y): (f32, f32), tile_size: f32) -> Option<usize> {
+        if x < 0.0 || y < 0.0 {
+            return None;
+        }
+        let in_bounds = |tile_x: isize, tile_y: isize| {
+            tile_x >= 0
+                && tile_y >= 0
+                && (tile_x as usize) < board.cols()
+                && (tile_y as usize) < board.rows()
+        };
+
+        match self {
+            Topology::Square | Topology::Torus => {
+                let (tile_x, tile_y) = ((x / tile_size) as isize, (y / tile_size) as isize);
+                if in_bounds(tile_x, tile_y) {
+                    Some(board.index_of((tile_x as usize, tile_y as usize)))
+                } else {
+                    None
+                }
+            }
+            Topology::Hex => {
+                // The hexagons of two adjacent rows overlap vertically, so the position is checked against both rows.
+                let row = (y / (0.75 * tile_size)) as isize;
+                for tile_y in [row, row - 1].iter().copied() {
+                    let offset = if tile_y % 2 == 1 {
+                        0.5 * tile_size
+                    } else {
+                        0.0
+                    };
+                    let tile_x = ((x - offset) / tile_size).floor() as isize;
+                    if !in_bounds(tile_x, tile_y) {
+                        continue;
+                    }
+
+                    // Position relative to the top-left corner of the hexagon's sprite.
+                    let u = x - offset - tile_x as f32 * tile_size;
+                    let v = y - tile_y as f32 * 0.75 * tile_size;
+                    let slope = (u - tile_size / 2.0).abs() / 2.0;
+                    if v >= slope && v <= tile_size - slope {
+                        return Some(board.index_of((tile_x as usize, tile_y as usize)));
+                    }
+                }
+                None
+            }
+            Topology::Triangle => {
+                // Triangles are half a tile apart, so each position is covered by the sprites of two triangles.
+                let tile_y = (y / tile_size) as isize;
+                let column = (x / (0.5 * tile_size)) as isize;
+                for tile_x in [column, column - 1].iter().copied() {
+                    if !in_bounds(tile_x, tile_y) {
+                        continue;
+                    }
+
+                    // Position relative to the top-left corner of the triangle's sprite.
+                    let u = x - tile_x as f32 * 0.5 * tile_size;
+                    let v = y - tile_y as f32 * tile_size;
+                    let height = if (tile_x + tile_y) % 2 == 0 {
+                        v
+                    } else {
+                        tile_size - v
+                    };
+                    if (u - tile_size / 2.0).abs() <= height / 2.0 {
+                        return Some(board.index_of((tile_x as usize, tile_y as usize)));
+                    }
+                }
+                None
+            }
+            Topology::Cube { .. } => {
+                // Each layer takes up its columns plus a gap of one tile.
+                let (column, tile_y) = ((x / tile_size) as usize, (y / tile_size) as usize);
+                let (tile_x, tile_z) = (column % (board.cols() + 1), column / (board.cols() + 1));
+                if tile_x < board.cols() && tile_y < board.rows() && tile_z < board.layers() {
+                    Some(board.index_of_3d((tile_x, tile_y, tile_z)))
+                } else {
+                    None
+                }
+            }
+            Topology::Graph { positions, .. } => positions.iter().position(|&(tile_x, tile_y)| {
+                x >= tile_x * tile_size
+                    && x < (tile_x + 1.0) * tile_size
+                    && y >= tile_y * tile_size
+                    && y < (tile_y + 1.0) * tile_size
+            }),
+        }
+    }
+
+    /// The amount of rows of sprites in the spritesheet, one for each orientation of the tiles.
+    pub fn sprite_rows(&self) -> usize {
+        match self {
+            Topology::Triangle => 2,
+            _ => 1,
+        }
+    }
+
+    /// The row of the spritesheet holding the sprites for the given tile.
+    pub fn sprite_row(&self, board: &Board, tile: usize) -> usize {
+        match self {
+            Topology::Triangle => {
+                let (x, y) = board.coords_of(tile);
+                (x + y) % 2
+            }
+            _ => 0,
+        }
+    }
+
+    /// Loads a graph of tiles from a file.
+    ///
+    /// Each line of the file is either empty, a comment starting with `#`, or one of:
+    /// - `tile <x> <y>`, adding a tile drawn at the given position (in tiles). Tiles are numbered from 0 in the order they are added.
+    /// - `edge <a> <b>`, making tiles `a` and `b` neighbours of each other.
+    pub fn load_graph(path: &Path) -> Result<Topology, GraphError> {
+        let contents = fs::read_to_string(path).map_err(GraphError::Io)?;
+        let mut positions: Vec<(f32, f32)> = Vec::new();
+        let mut neighbours: Vec<Vec<usize>> = Vec::new();
+
+        for (i, line) in contents.lines().enumerate() {
+            let invalid = |reason: &str| GraphError::InvalidLine {
+                line: i + 1,
+                reason: reason.to_string(),
+            };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                [] => {}
+                [comment, ..] if comment.starts_with('#') => {}
+                ["tile", x, y] => {
+                    let x: f32 = x.parse().map_err(|_| invalid("x is not a number"))?;
+                    let y: f32 = y.parse().map_err(|_| invalid("y is not a number"))?;
+                    if x < 0.0 || y < 0.0 {
+                        return Err(invalid("tile positions can't be negative"));
+                    }
+                    positions.push((x, y));
+                    neighbours.push(Vec::new());
+                }
+                ["edge", a, b] => {
+                    let a: usize = a.parse().map_err(|_| invalid("a is not a tile number"))?;
+                    let b: usize = b.parse().map_err(|_| invalid("b is not a tile number"))?;
+                    if a >= positions.len() || b >= positions.len() {
+                        return Err(invalid("edges can only connect tiles that have been added"));
+                    }
+                    if a != b && !neighbours[a].contains(&b) {
+                        neighbours[a].push(b);
+                        neighbours[b].push(a);
+                    }
+                }
+                _ => return Err(invalid("expected `tile <x> <y>` or `edge <a> <b>`")),
+            }
+        }
+
+        if positions.is_empty() {
+            return Err(GraphError::Empty);
+        }
+        // A tile's number has to fit in the tile, even if all of its neighbours are mines.
+        if let Some((tile, tile_neighbours)) = neighbours
+            .iter()
+            .enumerate()
+            .find(|(_, tile_neighbours)| tile_neighbours.len() > Tile::MAX_NUMBER as usize)
+        {
+            return Err(GraphError::TooManyNeighbours {
+                tile,
+                neighbours: tile_neighbours.len(),
+            });
+        }
+        Ok(Topology::Graph {
+            positions,
+            neighbours,
+        })
+    }
+}
+
+/// Errors that can occur when loading a graph of tiles from a file.
+#[derive(Debug)]
+pub enum GraphError {
+    Io(std::io::Error),
+    InvalidLine {
+        line: usize,
+        reason: String,
+    },
+    Empty,
+    /// A tile with more neighbours than the largest number a tile can show.
+    TooManyNeighbours {
+        tile: usize,
+        neighbours: usize,
+    },
+}
+
+impl fmt::Display for GraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphError::Io(e) => write!(f, "Could not read the board file: {}", e),
+            GraphError::InvalidLine { line, reason } => {
+                write!(f, "Invalid board file, line {}: {}", line, reason)
+            }
+            GraphError::Empty => write!(f, "Invalid board file: it doesn't contain any tiles"),
+            GraphError::TooManyNeighbours { tile, neighbours } => write!(
+                f,
+                "Invalid board file: tile {} has {} neighbours, but a tile can have at most {}",
+                tile,
+                neighbours,
+                Tile::MAX_NUMBER
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GraphError {}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Topology::Square => "Regular",
+            Topology::Torus => "Wrap-around (torus)",
+            Topology::Hex => "Hexagonal",
+            Topology::Triangle => "Triangular",
+            Topology::Cube { .. } => "3D cube",
+            Topology::Graph { .. } => "Custom board from file",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Loads a graph of a tile in the middle of the given amount of tiles around it.
+    fn load_star(tiles_around: usize) -> Result<Topology, GraphError> {
+        let mut contents = String::from("tile 0 0\n");
+        for tile in 1..=tiles_around {
+            contents += &format!("tile {} 1\nedge 0 {}\n", tile, tile);
+        }
+        let path = std::env::temp_dir().join(format!("rustsweeper-star-{}.txt", tiles_around));
+        fs::write(&path, contents).unwrap();
+        let result = Topology::load_graph(&path);
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    /// The neighbours of the tile at the given coordinates, as coordinates.
+    fn neighbour_coords(
+        topology: &Topology,
+        board: &Board,
+        coords: (usize, usize),
+    ) -> Vec<(usize, usize)> {
+        let mut neighbours: Vec<(usize, usize)> = topology
+            .neighbours(board, board.index_of(coords), Neighbourhood::Adjacent)
+            .into_iter()
+            .map(|tile| board.coords_of(tile))
+            .collect();
+        neighbours.sort_unstable_by_key(|&(x, y)| (y, x));
+        neighbours
+    }
+
+    /// Checks that the middle of every tile's sprite is found to be that tile.
+    fn assert_sprite_middles_hit_their_tiles(topology: &Topology, board: &Board) {
+        let tile_size = 32.0;
+        for tile in 0..board.len() {
+            let (x, y) = topology.tile_position(board, tile, tile_size);
+            let middle = (x + tile_size / 2.0, y + tile_size / 2.0);
+            assert_eq!(topology.tile_at(board, middle, tile_size), Some(tile));
+        }
+    }
+
+    #[test]
+    fn torus_neighbours_wrap_around_the_edges() {
+        let board = Board::new(4, 3);
+        assert_eq!(
+            neighbour_coords(&Topology::Torus, &board, (0, 0)),
+            vec![
+                (1, 0),
+                (3, 0),
+                (0, 1),
+                (1, 1),
+                (3, 1),
+                (0, 2),
+                (1, 2),
+                (3, 2)
+            ]
+        );
+        // Wrapping around a narrow torus reaches the same tiles from both sides.
+        let narrow = Board::new(2, 2);
+        assert_eq!(
+            Topology::Torus.neighbours(&narrow, 0, Neighbourhood::Adjacent),
+            vec![1, 2, 3]
+        );
+        assert_sprite_middles_hit_their_tiles(&Topology::Torus, &board);
+    }
+
+    #[test]
+    fn hex_neighbours_depend_on_the_row() {
+        let board = Board::new(4, 4);
+        // Odd rows are shifted half a tile to the right.
+        assert_eq!(
+            neighbour_coords(&Topology::Hex, &board, (1, 2)),
+            vec![(0, 1), (1, 1), (0, 2), (2, 2), (0, 3), (1, 3)]
+        );
+        assert_eq!(
+            neighbour_coords(&Topology::Hex, &board, (1, 1)),
+            vec![(1, 0), (2, 0), (0, 1), (2, 1), (1, 2), (2, 2)]
+        );
+        assert_eq!(neighbour_coords(&Topology::Hex, &board, (0, 0)).len(), 2);
+        assert_sprite_middles_hit_their_tiles(&Topology::Hex, &board);
+        // The top-left corner of the first sprite is outside of its hexagon.
+        assert_eq!(Topology::Hex.tile_at(&board, (0.0, 0.0), 32.0), None);
+    }
+
+    #[test]
+    fn triangle_neighbours_share_a_corner() {
+        let board = Board::new(5, 3);
+        // Pointing upwards, with 3 neighbours above and 5 below.
+        assert_eq!(
+            neighbour_coords(&Topology::Triangle, &board, (1, 1)),
+            vec![
+                (0, 0),
+                (1, 0),
+                (2, 0),
+                (0, 1),
+                (2, 1),
+                (3, 1),
+                (0, 2),
+                (1, 2),
+                (2, 2),
+                (3, 2)
+            ]
+        );
+        // Pointing downwards, with 5 neighbours above and 3 below.
+        assert_eq!(
+            neighbour_coords(&Topology::Triangle, &board, (2, 1)),
+            vec![
+                (0, 0),
+                (1, 0),
+                (2, 0),
+                (3, 0),
+                (4, 0),
+                (0, 1),
+                (1, 1),
+                (3, 1),
+                (4, 1),
+                (1, 2),
+                (2, 2),
+                (3, 2)
+            ]
+        );
+        assert_sprite_middles_hit_their_tiles(&Topology::Triangle, &board);
+        assert_eq!(Topology::Triangle.tile_at(&board, (0.0, 0.0), 32.0), None);
+    }
+
+    #[test]
+    fn cube_neighbours_fill_the_surrounding_cube() {
+        let board = Board::with_layers(3, 3, 3);
+        let topology = Topology::Cube { layers: 3 };
+        let middle = board.index_of_3d((1, 1, 1));
+        let mut neighbours = topology.neighbours(&board, middle, Neighbourhood::Adjacent);
+        neighbours.sort_unstable();
+        assert_eq!(
+            neighbours,
+            (0..27).filter(|&tile| tile != middle).collect::<Vec<_>>()
+        );
+        let corner = board.index_of_3d((0, 0, 0));
+        assert_eq!(
+            topology
+                .neighbours(&board, corner, Neighbourhood::Adjacent)
+                .len(),
+            7
+        );
+        assert_sprite_middles_hit_their_tiles(&topology, &board);
+        // The gap between the first two layers.
+        assert_eq!(topology.tile_at(&board, (3.5 * 32.0, 16.0), 32.0), None);
+    }
+
+    #[test]
+    fn graphs_with_too_many_neighbours_are_rejected() {
+        let most = Tile::MAX_NUMBER as usize;
+        let graph = load_star(most).unwrap();
+        assert_eq!(graph.most_neighbours(Neighbourhood::Adjacent), most);
+        assert!(matches!(
+            load_star(most + 1),
+            Err(GraphError::TooManyNeighbours { tile: 0, neighbours }) if neighbours == most + 1
+        ));
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use crate::{
+    board::MineDistributionError,
+    game::{Action, GameContainer, GameMode, GameState},
+    net::{Connection, Message},
+};
+
+/// A game of "Flags" between two players, who take turns revealing tiles on the same board.
+///
+/// Revealing a mine claims it for the player and grants another turn, revealing a safe tile passes the turn.
+/// The player who claims the majority of the mines wins.
+///
+/// Over the network, both players keep a copy of the board and send each other their actions.
+/// Only one player may act at a time, so the actions arrive in the same order on both ends.
+pub(crate) struct Versus {
+    /// The names of the players, by player number.
+    pub(crate) names: [String; 2],
+    /// The amount of mines each player has claimed.
+    pub(crate) scores: [usize; 2],
+    /// The player whose turn it is.
+    pub(crate) turn: usize,
+    /// The player who claimed each revealed mine, by tile.
+    pub(crate) claims: HashMap<usize, usize>,
+    /// The connection to the other player, `None` when both players share this computer.
+    connection: Option<Connection>,
+    /// The player of this instance in a network game. The host is player 0.
+    player: usize,
+}
+
+impl Versus {
+    /// A game between two players sharing this computer, who take turns with the mouse.
+    pub(crate) fn hot_seat(names: [String; 2]) -> Versus {
+        Versus {
+            names,
+            scores: [0, 0],
+            turn: 0,
+            claims: HashMap::new(),
+            connection: None,
+            player: 0,
+        }
+    }
+
+    /// A game over the network, against the player at the other end of the connection.
+    /// The names are only known to the host, who sends them to the other player.
+    pub(crate) fn network(connection: Connection, player: usize, names: [String; 2]) -> Versus {
+        let mut connection = connection;
+        if player == 0 {
+            for (player, name) in names.iter().enumerate() {
+                connection.broadcast(&Message::Player {
+                    player,
+                    name: name.clone(),
+                });
+            }
+        }
+        Versus {
+            connection: Some(connection),
+            player,
+            ..Versus::hot_seat(names)
+        }
+    }
+
+    pub(crate) fn is_network_game(&self) -> bool {
+        self.connection.is_some()
+    }
+
+    /// Whether the player at this computer may act, i.e. whether it is their turn.
+    pub(crate) fn is_local_turn(&self) -> bool {
+        self.connection.is_none() || self.turn == self.player
+    }
+
+    /// The player with the most mines, or `None` on a draw.
+    pub(crate) fn leader(&self) -> Option<usize> {
+        match self.scores[0].cmp(&self.scores[1]) {
+            std::cmp::Ordering::Greater => Some(0),
+            std::cmp::Ordering::Less => Some(1),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+}
+
+impl GameContainer {
+    /// Reveals a tile as the player whose turn it is. The mines are distributed on the first turn of the game.
+    pub(crate) fn take_turn(&mut self, tile_index: usize) -> Result<(), MineDistributionError> {
+        if self.board[tile_index].is_revealed() {
+            return Ok(());
+        }
+        // The first tile may be a mine, which doesn't count as revealed, so the mines decide whether it is the first.
+        if self.mine_tiles == 0 {
+            self.distribute_mines(tile_index)?;
+        }
+        let versus = match &mut self.mode {
+            GameMode::Versus(versus) => versus,
+            _ => return Ok(()),
+        };
+        let tile = self.board[tile_index];
+        if tile.is_a_mine() {
+            self.clock.start();
+            self.board[tile_index].set_revealed(true);
+            self.tiles_flagged += tile.mines() as isize;
+            versus.claims.insert(tile_index, versus.turn);
+            versus.scores[versus.turn] += tile.mines() as usize;
+            let majority = versus.scores[versus.turn] * 2 > self.game_mines;
+            if majority || self.tiles_flagged as usize == self.game_mines {
+                self.state = GameState::Won;
+                self.stats.final_time = Some(self.clock.stop());
+            }
+        } else {
+            versus.turn = 1 - versus.turn;
+            self.reveal_tile_at(tile_index);
+        }
+        Ok(())
+    }
+
+    /// Handles the actions and names that have arrived from the other player of a versus game.
+    /// Actions taken out of turn are ignored.
+    pub(crate) fn poll_versus(&mut self) -> Result<(), MineDistributionError> {
+        let messages = match &self.mode {
+            GameMode::Versus(Versus {
+                connection: Some(connection),
+                ..
+            }) => connection.receive(),
+            _ => return Ok(()),
+        };
+        let mut result = Ok(());
+        for (_, message) in messages {
+            let versus = match &mut self.mode {
+                GameMode::Versus(versus) => versus,
+                _ => return result,
+            };
+            match message {
+                Message::Action {
+                    player,
+                    action: Action::Reveal(tile_index),
+                } if player == versus.turn
+                    && player != versus.player
+                    && tile_index < self.board.len() =>
+                {
+                    result = result.and(self.record(player, Action::Reveal(tile_index)));
+                }
+                Message::Player { player, name } if player < 2 => versus.names[player] = name,
+                _ => {}
+            }
+        }
+        result
+    }
+
+    /// Sends an action of this player to the other player of a versus game.
+    pub(crate) fn send_versus_action(&mut self, player: usize, action: Action) {
+        if let GameMode::Versus(Versus {
+            connection: Some(connection),
+            ..
+        }) = &mut self.mode
+        {
+            connection.broadcast(&Message::Action { player, action });
+        }
+    }
+}